@@ -1,14 +1,29 @@
+use std::collections::HashSet;
+
 use config_watcher::ConfigItemWatcherHandle;
 pub use hc_homie5::connection::{ConnectionEvent, ConnectionState};
 use hc_homie5::controller::MetaOverlayHandler;
+use hc_homie5::util::DebouncedSender;
 use homie5::{DeviceRef, PropertyRef};
 use simple_kv_store::KeyValueStore;
 use tokio::sync::mpsc::Sender;
 
 use crate::{
-    cron_manager::CronManager, device_manager::DeviceManager, lua_runtime::LuaModuleManager, meta::MetaManager,
-    mqtt_client::ManagedMqttClient, rule_manager::RuleManager, rules::RuleContext, solar_events::SolarEventManager,
-    timer_manager::TimerManager, virtual_devices::VirtualDeviceManager,
+    calendar::CalendarManager,
+    cron_manager::CronManager,
+    deferred_trigger_manager::DeferredTriggerManager,
+    device_manager::DeviceManager,
+    lua_runtime::LuaModuleManager,
+    meta::MetaManager,
+    mqtt_client::ManagedMqttClient,
+    rule_manager::RuleManager,
+    rules::RuleContext,
+    solar_events::SolarEventManager,
+    system_events::{SystemEvent, SystemEventKind},
+    timer_manager::TimerManager,
+    trigger_state::TriggerStateStore,
+    value_store_events::ValueStoreNotifier,
+    virtual_devices::VirtualDeviceManager,
 };
 
 #[derive(Debug)]
@@ -18,6 +33,8 @@ pub enum AppEvent {
     CancelPropertyValueReadFromMqtt(PropertyRef),
     UpdateVirtualDevicesQueries(DeviceRef),
 
+    System(SystemEvent),
+
     Exit,
 }
 pub struct AppState {
@@ -25,22 +42,39 @@ pub struct AppState {
     pub rules: RuleManager,
     pub vdm: VirtualDeviceManager,
     pub timers: TimerManager,
+    pub deferred_triggers: DeferredTriggerManager,
+    pub trigger_states: TriggerStateStore,
     pub solar_events: SolarEventManager,
     pub cron: CronManager,
     pub mqtt_client: ManagedMqttClient,
     pub app_event_sender: Sender<AppEvent>,
     pub should_exit: bool,
+    /// set once the startup system event was handled
+    pub startup_completed: bool,
+    /// set after a reconnect to the broker until the reconnect system event was handled
+    pub reconnect_pending: bool,
+    /// debounces startup, reconnect and reload system events until discovery and configuration changes settled
+    pub system_events: DebouncedSender<AppEvent>,
+    /// devices which were discovered but did not yet publish their description
+    pub new_devices: HashSet<DeviceRef>,
+    /// devices which published their description since the application started, kept across rediscovery so that
+    /// only devices which are really new count as added
+    pub known_devices: HashSet<DeviceRef>,
     pub mqtt_state: ConnectionState,
     pub discovery_state: ConnectionState,
     pub virtual_devices_state: ConnectionState,
     pub value_store: KeyValueStore,
+    pub value_store_events: ValueStoreNotifier,
     pub lua_module_manager: LuaModuleManager,
+    pub calendars: CalendarManager,
     pub meta: MetaManager,
     pub meta_handler: MetaOverlayHandler,
     pub rule_watcher_handle: ConfigItemWatcherHandle,
     pub virtual_devices_watcher_handle: ConfigItemWatcherHandle,
     pub meta_watcher_handle: ConfigItemWatcherHandle,
     pub lua_files_watcher_handle: ConfigItemWatcherHandle,
+    /// `None` if calendars are disabled
+    pub calendars_watcher_handle: Option<ConfigItemWatcherHandle>,
 }
 
 impl AppState {
@@ -48,11 +82,16 @@ impl AppState {
         RuleContext {
             rules: &self.rules,
             timers: &self.timers,
+            deferred: &self.deferred_triggers,
+            trigger_states: &self.trigger_states,
             dm: &self.dm,
             vdm: &self.vdm,
             mqtt_client: &self.mqtt_client,
             value_store: &self.value_store,
+            value_store_events: &self.value_store_events,
             lmm: &self.lua_module_manager,
+            calendars: &self.calendars,
+            solar_events: &self.solar_events,
         }
     }
 
@@ -93,6 +132,55 @@ impl AppState {
                     log::error!("Error starting meta config watcher. {:?}", e);
                 }
             }
+
+            if let Some(calendars_watcher_handle) = &self.calendars_watcher_handle {
+                match calendars_watcher_handle.start().await {
+                    Ok(_) => {
+                        log::debug!("Started calendars config watcher");
+                    }
+                    Err(e) => {
+                        log::error!("Error starting calendars config watcher. {:?}", e);
+                    }
+                }
+            }
+
+            self.delay_startup_event().await;
+        }
+    }
+
+    /// (Re)schedules the startup system event. Every call before the startup event was handled delays
+    /// it further, this way it is only emitted once discovery and rule loading settled. The same applies to the
+    /// reconnect system event while devices are rediscovered after a reconnect.
+    pub async fn delay_startup_event(&self) {
+        if !self.startup_completed {
+            self.system_events
+                .send(AppEvent::System(SystemEvent::new(SystemEventKind::Startup)))
+                .await;
+        } else if self.reconnect_pending {
+            self.system_events
+                .send(AppEvent::System(SystemEvent::new(SystemEventKind::Reconnect)))
+                .await;
+        }
+    }
+
+    /// Signals a reconnect to the broker. The reconnect system event is emitted once rediscovery settled, a
+    /// reconnect before startup is part of the startup.
+    pub async fn reconnected(&mut self) {
+        if self.startup_completed {
+            self.reconnect_pending = true;
+        }
+        self.delay_startup_event().await;
+    }
+
+    /// Signals a changed configuration (rules, lua files, calendars). After startup this emits a debounced reload
+    /// system event, before startup (or a pending reconnect) it delays the startup (or reconnect) event.
+    pub async fn config_changed(&self, detail: &str) {
+        if self.startup_completed && !self.reconnect_pending {
+            self.system_events
+                .send(AppEvent::System(SystemEvent::with_detail(SystemEventKind::Reload, detail)))
+                .await;
+        } else {
+            self.delay_startup_event().await;
         }
     }
 }
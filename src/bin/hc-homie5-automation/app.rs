@@ -1,8 +1,9 @@
-use std::{fs, iter, time::Duration};
+use std::{collections::HashSet, fs, iter, time::Duration};
 
 use color_eyre::eyre::Result;
 use hc_homie5_automation::{
     app_state::{AppEvent, AppState, ConnectionState},
+    calendar::{Calendar, CalendarManager},
     lua_runtime::LuaModuleManager,
     meta::{MetaConfig, MetaManager},
     utils::throttle_channel,
@@ -13,29 +14,42 @@ use tokio::sync::mpsc::{self};
 
 use crate::eventloop::EventMultiPlexer;
 
-use crate::settings::{ConfigBackend, ValueStoreConfig, CHANNEL_CAPACITY, SETTINGS};
+use crate::settings::{ConfigBackend, ValueStoreConfig, CHANNEL_CAPACITY, SETTINGS, SYSTEM_EVENT_SETTLE_TIME};
 use config_watcher::{backend, config_item_watcher::run_config_item_watcher, Tokenizer, WatcherError, YamlTokenizer};
 use hc_homie5::client::HomieClientHandle;
+use hc_homie5::util::DebouncedSender;
 use hc_homie5_automation::{
     cron_manager::CronManager,
+    deferred_trigger_manager::DeferredTriggerManager,
     device_manager::DeviceManager,
     mqtt_client::{run_mqtt_client, MqttClientHandle},
     rule_manager::RuleManager,
     rules::Rule,
     solar_events::{run_solar_event_task, SolarEventHandle},
     timer_manager::TimerManager,
+    trigger_state::TriggerStateStore,
+    value_store_events::ValueStoreNotifier,
     virtual_devices::{VirtualDeviceManager, VirtualDeviceSpec},
+    webhook::{run_webhook_listener, WebhookListenerHandle},
 };
-pub struct LuaFileTokenizer;
+/// Passes the whole document as a single item (lua modules and calendars are not split into documents)
+pub struct WholeFileTokenizer;
 
-impl Tokenizer for LuaFileTokenizer {
+impl Tokenizer for WholeFileTokenizer {
     fn tokenize<'a>(&self, content: &'a str) -> Box<dyn Iterator<Item = &'a str> + 'a> {
         Box::new(iter::once(content))
     }
 }
 
-pub async fn initialize_app(
-) -> Result<(EventMultiPlexer, HomieClientHandle, HomieClientHandle, MqttClientHandle, SolarEventHandle, AppState)> {
+pub async fn initialize_app() -> Result<(
+    EventMultiPlexer,
+    HomieClientHandle,
+    HomieClientHandle,
+    MqttClientHandle,
+    SolarEventHandle,
+    WebhookListenerHandle,
+    AppState,
+)> {
     let settings = &SETTINGS;
 
     set_fallback_homie_domain(settings.homie.homie_domain.clone());
@@ -75,7 +89,9 @@ pub async fn initialize_app(
     // =====================================================
     let (timers, timers_receiver) = TimerManager::new();
 
-    let (cron, cron_receiver) = CronManager::new();
+    let (cron, cron_receiver) = CronManager::new(settings.app.timezone.tz());
+
+    let (deferred_triggers, deferred_triggers_receiver) = DeferredTriggerManager::new();
 
     let (solar_event_handler, solar_events, solar_events_receiver) = run_solar_event_task(
         settings.app.location.latitude,
@@ -85,6 +101,11 @@ pub async fn initialize_app(
     );
     // let (solar_event_handler, solar_events, solar_events_receiver) = run_solar_event_task(48.166, 11.5683, 0.0, 10);
 
+    // Setup webhook listener
+    // =====================================================
+    let (webhook_listener_handle, webhook_receiver) =
+        run_webhook_listener(settings.app.webhook_listener.addr(), 1024).await?;
+
     // Setup configuration watchers
     // =====================================================
 
@@ -164,10 +185,61 @@ pub async fn initialize_app(
                 backend::run_mqtt_watcher(mco.to_mqtt_options().expect("MQTT TLS configuration error"), topic, 1024)
             }
         },
-        &LuaFileTokenizer,
+        &WholeFileTokenizer,
         deserialize_lua_file,
     )?;
 
+    let deserialize_calendar = |doc: &str| -> std::result::Result<Calendar, String> {
+        Calendar::parse(doc).map_err(|err| {
+            let msg = format!("Calendar parse error: {}", err);
+            log::error!("{}", msg);
+            msg
+        })
+    };
+
+    let (calendars_watcher_handle, calendars_receiver) = match settings.app.calendars_config.backend() {
+        Some(calendars_backend) => {
+            let (handle, receiver) = run_config_item_watcher::<Calendar, _>(
+                || match calendars_backend {
+                    ConfigBackend::File { path } => {
+                        let absolute_path = fs::canonicalize(path).unwrap_or_else(|_| {
+                            panic!(
+                                "Configured calendars folder [{}] does not exist!",
+                                path.as_os_str().to_string_lossy()
+                            )
+                        });
+                        // iCalendar and YAML files are told apart by their content
+                        backend::run_config_file_watcher(absolute_path, "*")
+                    }
+                    ConfigBackend::Kubernetes { name, namespace } => {
+                        log::debug!("Using Kubernetes backend for calendars");
+                        backend::run_configmap_watcher(name.to_string(), namespace.to_string())
+                    }
+                    ConfigBackend::Mqtt { topic } => {
+                        let mco = settings
+                            .homie
+                            .to_mqtt_client_config()
+                            .client_id(format!("{}-cfg-cal", &settings.homie.client_id));
+                        log::debug!("Using Mqtt backend for calendars");
+                        backend::run_mqtt_watcher(
+                            mco.to_mqtt_options().expect("MQTT TLS configuration error"),
+                            topic,
+                            1024,
+                        )
+                    }
+                },
+                &WholeFileTokenizer,
+                deserialize_calendar,
+            )?;
+            (Some(handle), receiver)
+        }
+        None => {
+            log::debug!("Calendars are disabled");
+            // the sender is dropped right away, the receiver never yields an event
+            (None, mpsc::channel(1).1)
+        }
+    };
+
     let deserialize_meta = |doc: &str| -> std::result::Result<MetaConfig, String> {
         serde_yaml_ng::from_str::<MetaConfig>(doc).map_err(|err| {
             let preview = doc.lines().take(3).collect::<Vec<_>>().join(" | ");
@@ -215,6 +287,10 @@ pub async fn initialize_app(
         } => KeyValueStore::Kubernetes(KubernetesStore::new(namespace, name, *ressource_type).await?),
         ValueStoreConfig::Sqlite { path } => KeyValueStore::SQLite(SQLiteStore::new(path).await),
     };
+    let (value_store_events, value_store_events_receiver) = ValueStoreNotifier::new();
+
+    // startup and reload system events are only emitted once things settled
+    let system_events = DebouncedSender::new(SYSTEM_EVENT_SETTLE_TIME, app_event_sender.clone());
 
     // Setup EventMultiPlexer
     // =====================================================
@@ -228,10 +304,14 @@ pub async fn initialize_app(
         throttle_channel(vdevices_receiver, Duration::from_millis(10)),
         lua_files_receiver,
         throttle_channel(meta_receiver, Duration::from_millis(10)),
+        calendars_receiver,
         timers_receiver,
         cron_receiver,
         mqtt_event_receiver,
         solar_events_receiver,
+        deferred_triggers_receiver,
+        value_store_events_receiver,
+        webhook_receiver,
     );
 
     Ok((
@@ -240,27 +320,38 @@ pub async fn initialize_app(
         homie_ctl_device_client_handle,
         mqtt_client_handle,
         solar_event_handler,
+        webhook_listener_handle,
         AppState {
             dm,
             rules: RuleManager::new(),
             vdm,
             timers,
+            deferred_triggers,
+            trigger_states: TriggerStateStore::new(),
             solar_events,
             cron,
             mqtt_client: mqtt_client.clone(),
             app_event_sender,
             should_exit: false,
+            new_devices: HashSet::new(),
+            known_devices: HashSet::new(),
+            startup_completed: false,
+            reconnect_pending: false,
+            system_events,
             mqtt_state: ConnectionState::Init,
             discovery_state: ConnectionState::Init,
             virtual_devices_state: ConnectionState::Init,
             lua_module_manager: LuaModuleManager::new(),
+            calendars: CalendarManager::new(),
             meta: MetaManager::new(settings.homie.homie_domain.clone(), mqtt_client.clone()),
             meta_handler: hc_homie5::controller::MetaOverlayHandler::new(settings.homie.homie_domain.clone()),
             value_store,
+            value_store_events,
             rule_watcher_handle: rules_watcher_handle,
             virtual_devices_watcher_handle: vdevices_watcher_handle,
             meta_watcher_handle,
             lua_files_watcher_handle,
+            calendars_watcher_handle,
         },
     ))
 }
@@ -270,9 +361,12 @@ pub async fn deinitialize_app(
     homie_ctrl_device_client_handle: HomieClientHandle,
     mqtt_client_handle: MqttClientHandle,
     solar_events_handle: SolarEventHandle,
+    webhook_listener_handle: WebhookListenerHandle,
 ) -> Result<()> {
     solar_events_handle.stop().await;
 
+    webhook_listener_handle.stop().await;
+
     mqtt_client_handle.stop().await?;
 
     // once the mqtt connection is closed the discovery task will exit.
@@ -3,7 +3,11 @@ use std::time::Duration;
 use color_eyre::eyre::Result;
 
 use hc_homie5::device::{HomieDevice, HomieDeviceCore};
-use hc_homie5_automation::app_state::{AppEvent, AppState};
+use hc_homie5_automation::{
+    app_state::{AppEvent, AppState},
+    rules::run_system_rules,
+    system_events::{SystemEvent, SystemEventKind},
+};
 
 pub async fn handle_app_event(event: AppEvent, state: &mut AppState) -> Result<bool> {
     match event {
@@ -21,12 +25,31 @@ pub async fn handle_app_event(event: AppEvent, state: &mut AppState) -> Result<b
                     .queries_virtual_device_updated(&device_ref, vdev.description());
             }
         }
+        AppEvent::System(event) => {
+            if event.kind == SystemEventKind::Startup {
+                // startup is only emitted once
+                if state.startup_completed {
+                    return Ok(false);
+                }
+                state.startup_completed = true;
+            } else if event.kind == SystemEventKind::Reconnect {
+                state.reconnect_pending = false;
+            }
+            log::debug!("System event: {}", event.kind);
+            run_system_rules(&event, &state.as_rule_ctx()).await;
+        }
         AppEvent::Exit => {
+            // run shutdown rules while all connections are still available
+            run_system_rules(&SystemEvent::new(SystemEventKind::Shutdown), &state.as_rule_ctx()).await;
+
             // Stop configuration watchers
             state.rule_watcher_handle.stop().await?;
             state.virtual_devices_watcher_handle.stop().await?;
             state.lua_files_watcher_handle.stop().await?;
             state.meta_watcher_handle.stop().await?;
+            if let Some(calendars_watcher_handle) = &mut state.calendars_watcher_handle {
+                calendars_watcher_handle.stop().await?;
+            }
 
             // stop discovery and send disconnect signal for all devices
             state.dm.stop_discover().await?;
@@ -46,9 +69,11 @@ pub async fn handle_app_event(event: AppEvent, state: &mut AppState) -> Result<b
             let mut devices = state.dm.write().await;
             devices.clear();
 
-            // clear active times and crons
+            // clear active times, crons, deferred triggers and trigger states
             state.timers.clear();
             state.cron.clear();
+            state.deferred_triggers.clear();
+            state.trigger_states.clear();
 
             // exit
             state.should_exit = true;
@@ -0,0 +1,11 @@
+use color_eyre::eyre::Result;
+use config_watcher::config_item_watcher::ConfigItemEvent;
+use hc_homie5_automation::{app_state::AppState, calendar::Calendar};
+
+pub async fn handle_calendars_changes_event(event: ConfigItemEvent<Calendar>, state: &mut AppState) -> Result<bool> {
+    if let ConfigItemEvent::New(..) | ConfigItemEvent::Removed(..) = event {
+        state.config_changed("calendars").await;
+    }
+    state.calendars.handle_event(event);
+    Ok(false)
+}
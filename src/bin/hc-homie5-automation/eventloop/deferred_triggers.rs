@@ -0,0 +1,10 @@
+use color_eyre::eyre::Result;
+use hc_homie5_automation::{
+    app_state::AppState, deferred_trigger_manager::DeferredTriggerEvent, rules::run_deferred_trigger_rules,
+};
+
+pub async fn handle_deferred_trigger_event(event: DeferredTriggerEvent, state: &mut AppState) -> Result<bool> {
+    log::debug!("DeferredTriggerEvent: {:?}", event);
+    run_deferred_trigger_rules(&event, &state.as_rule_ctx()).await;
+    Ok(false)
+}
@@ -3,10 +3,10 @@ use hc_homie5::client::HomieClientEvent;
 use hc_homie5::model::DiscoveryAction;
 use hc_homie5_automation::{
     app_state::{AppState, ConnectionEvent, ConnectionState},
-    rules::run_subject_rules,
+    rules::{run_broadcast_rules, run_device_added_rules, run_device_rules, run_subject_rules},
     utils::log_homie_message,
 };
-use homie5::ToTopic;
+use homie5::{Homie5Message, ToTopic};
 
 pub async fn handle_discovery_client_event(event: HomieClientEvent, state: &mut AppState) -> Result<bool> {
     match event {
@@ -16,9 +16,14 @@ pub async fn handle_discovery_client_event(event: HomieClientEvent, state: &mut
                 let mut devices = state.dm.write().await;
                 devices.clear();
                 drop(devices);
+                state.new_devices.clear();
                 state.meta_handler.clear();
+                state.dm.discover().await?;
+                // the reconnect system event is emitted once rediscovery settled
+                state.reconnected().await;
+            } else {
+                state.dm.discover().await?;
             }
-            state.dm.discover().await?;
             state.start_watchers().await;
         }
         HomieClientEvent::Disconnect => {
@@ -30,12 +35,21 @@ pub async fn handle_discovery_client_event(event: HomieClientEvent, state: &mut
             let action = state.dm.discovery_handle_event(event).await?;
             if let Some(action) = action {
                 match action {
+                    DiscoveryAction::Unhandled(ref message @ Homie5Message::Broadcast { .. }) => {
+                        run_broadcast_rules(message, &state.as_rule_ctx()).await;
+                    }
                     DiscoveryAction::Unhandled(_) => {
                         // ignore unhandled messages
                     }
+                    DiscoveryAction::NewDevice { ref device, .. } => {
+                        // remember the device until its description is received
+                        state.new_devices.insert(device.clone());
+                        state.delay_startup_event().await;
+                    }
                     // device added / changed
                     DiscoveryAction::DeviceDescriptionChanged(ref device_ref) => {
                         log::debug!("Device discovered/updated: {}", device_ref.to_topic().build());
+                        state.delay_startup_event().await;
                         // Write lock scope: apply pending meta overlays, then drop
                         {
                             let mut devices = state.dm.write().await;
@@ -52,10 +66,21 @@ pub async fn handle_discovery_client_event(event: HomieClientEvent, state: &mut
                         // iterate over all rules and update any materialized queries with the
                         // updated/added device
                         state.rules.queries_device_updated(device_ref, desc);
+                        drop(devices);
+
+                        // devices of the initial discovery and rediscovered devices are not added
+                        if state.new_devices.remove(device_ref)
+                            && state.known_devices.insert(device_ref.clone())
+                            && state.startup_completed
+                        {
+                            run_device_added_rules(device_ref, &state.as_rule_ctx()).await;
+                        }
                     }
                     // device removed
-                    DiscoveryAction::DeviceRemoved(device) => {
+                    DiscoveryAction::DeviceRemoved(ref device) => {
                         log::debug!("Device removed: {}", device.ident.to_topic().build());
+                        state.new_devices.remove(&device.ident);
+                        state.known_devices.remove(&device.ident);
                         // iterate over all rules and update any materialized queries with the
                         // removed device
 
@@ -64,9 +89,17 @@ pub async fn handle_discovery_client_event(event: HomieClientEvent, state: &mut
                         state
                             .rules
                             .queries_device_removed(&device.ident, device.description.as_ref());
+
+                        run_device_rules(&action, &state.as_rule_ctx()).await;
                     }
-                    DiscoveryAction::StateChanged { device, from, to } => {
+                    DiscoveryAction::StateChanged { ref device, from, to } => {
                         log::debug!("Device state changed: {}: {} -> {}", device.to_topic().build(), from, to);
+                        run_device_rules(&action, &state.as_rule_ctx()).await;
+                    }
+                    DiscoveryAction::DeviceAlert { .. }
+                    | DiscoveryAction::DeviceAlertChanged { .. }
+                    | DiscoveryAction::DeviceAlertCleared { .. } => {
+                        run_device_rules(&action, &state.as_rule_ctx()).await;
                     }
                     _ => {
                         run_subject_rules(&action, &state.as_rule_ctx()).await;
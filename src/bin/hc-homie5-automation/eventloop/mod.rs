@@ -1,18 +1,24 @@
+use calendars::handle_calendars_changes_event;
 use color_eyre::eyre::Result;
 use config_watcher::config_item_watcher::ConfigItemEvent;
 use cron::handle_cron_event;
+use deferred_triggers::handle_deferred_trigger_event;
 use discovery::handle_discovery_client_event;
 use hc_homie5::client::HomieClientEvent;
 use hc_homie5::define_event_multiplexer;
 use hc_homie5_automation::{
     app_state::{AppEvent, AppState},
+    calendar::Calendar,
     cron_manager::CronEvent,
+    deferred_trigger_manager::DeferredTriggerEvent,
     meta::MetaConfig,
     mqtt_client::MqttClientEvent,
     rules::Rule,
     solar_events::SolarEvent,
     timer_manager::TimerEvent,
+    value_store_events::ValueStoreEvent,
     virtual_devices::VirtualDeviceSpec,
+    webhook::WebhookEvent,
 };
 use lua_files::handle_lua_files_changes_event;
 use meta::handle_meta_changes_event;
@@ -20,10 +26,14 @@ use mqtt_client::handle_mqtt_client_event;
 use rules::handle_rules_changes_event;
 use solar::handle_solar_event;
 use timers::handle_timer_event;
+use value_store::handle_value_store_event;
 use virtual_devices::{handle_virtual_devices_changes_event, handle_virtual_devices_client_event};
+use webhook::handle_webhook_event;
 
 mod app;
+mod calendars;
 mod cron;
+mod deferred_triggers;
 mod discovery;
 mod lua_files;
 mod meta;
@@ -31,7 +41,9 @@ mod mqtt_client;
 mod rules;
 mod solar;
 mod timers;
+mod value_store;
 mod virtual_devices;
+mod webhook;
 
 pub use app::*;
 
@@ -45,10 +57,14 @@ define_event_multiplexer! {
         VirtualDevicesChanges(ConfigItemEvent<VirtualDeviceSpec>) => vdevice_changes,
         LuaFilesChanges(ConfigItemEvent<String>) => lua_changes,
         MetaChanges(ConfigItemEvent<MetaConfig>) => meta_changes,
+        CalendarsChanges(ConfigItemEvent<Calendar>) => calendar_changes,
         TimerEvent(TimerEvent) => timer_event,
         CronEvent(CronEvent) => cron_event,
         MqttClientEvent(MqttClientEvent) => mqtt_client_event,
         SolarEvent(SolarEvent) => solar_event,
+        DeferredTriggerEvent(DeferredTriggerEvent) => deferred_trigger_event,
+        ValueStoreEvent(ValueStoreEvent) => value_store_event,
+        WebhookEvent(WebhookEvent) => webhook_event,
     }
 }
 
@@ -73,10 +89,16 @@ pub async fn run_event_loop(event_multiplexer: &mut EventMultiPlexer, state: &mu
                 handle_lua_files_changes_event(config_file_event, state).await?
             }
             Event::MetaChanges(config_file_event) => handle_meta_changes_event(config_file_event, state).await?,
+            Event::CalendarsChanges(config_file_event) => {
+                handle_calendars_changes_event(config_file_event, state).await?
+            }
             Event::TimerEvent(timer_event) => handle_timer_event(timer_event, state).await?,
             Event::CronEvent(cron_event) => handle_cron_event(cron_event, state).await?,
             Event::MqttClientEvent(mqtt_event) => handle_mqtt_client_event(mqtt_event, state).await?,
             Event::SolarEvent(solar_event) => handle_solar_event(solar_event, state).await?,
+            Event::DeferredTriggerEvent(deferred_event) => handle_deferred_trigger_event(deferred_event, state).await?,
+            Event::ValueStoreEvent(value_store_event) => handle_value_store_event(value_store_event, state).await?,
+            Event::WebhookEvent(webhook_event) => handle_webhook_event(webhook_event, state).await?,
             Event::Timeout => state.should_exit,
             Event::None => false,
         };
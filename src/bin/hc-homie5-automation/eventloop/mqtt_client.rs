@@ -13,6 +13,7 @@ pub async fn handle_mqtt_client_event(event: MqttClientEvent, state: &mut AppSta
             if let Some(ConnectionEvent::Reconnect) = con_event {
                 state.mqtt_client.resubscribe().await?;
                 state.meta.republish_all().await?;
+                state.reconnected().await;
             }
             state.start_watchers().await;
         }
@@ -21,20 +22,19 @@ pub async fn handle_mqtt_client_event(event: MqttClientEvent, state: &mut AppSta
             state.mqtt_state.change_state(ConnectionState::Disconnected);
         }
         MqttClientEvent::PublishMessage(publish) => {
-            log::debug!("MQTT value received: {} = {}", publish.topic, publish.payload);
-            match state
-                .vdm
-                .update_member_value_mqtt(&publish.topic, &publish.payload)
-                .await
-            {
-                Ok(_) => {}
-                Err(err) => {
-                    log::warn!(
-                        "Error updating virtual devices with value for {} - {}: {}",
-                        publish.topic,
-                        publish.payload,
-                        err
-                    );
+            log::debug!("MQTT value received: {} = {}", publish.topic, String::from_utf8_lossy(&publish.payload));
+            // virtual device members only handle text payloads
+            if let Some(payload) = publish.payload_str() {
+                match state.vdm.update_member_value_mqtt(&publish.topic, payload).await {
+                    Ok(_) => {}
+                    Err(err) => {
+                        log::warn!(
+                            "Error updating virtual devices with value for {} - {}: {}",
+                            publish.topic,
+                            payload,
+                            err
+                        );
+                    }
                 }
             }
             run_mqtt_rules(&publish, &state.as_rule_ctx()).await;
@@ -10,6 +10,10 @@ pub async fn handle_rules_changes_event(event: ConfigItemEvent<Rule>, state: &mu
                 rule.name,
                 state.rules.get_filename(hash).unwrap_or(&"-".to_string()),
             );
+            state
+                .value_store_events
+                .load_values(&state.value_store, rule.while_value_store_keys())
+                .await;
             state
                 .rules
                 .add_rule(
@@ -20,13 +24,23 @@ pub async fn handle_rules_changes_event(event: ConfigItemEvent<Rule>, state: &mu
                     &state.solar_events,
                     &state.dm,
                     &*state.vdm.read().await,
+                    &state.deferred_triggers,
                 )
                 .await?;
+            state.config_changed("rules").await;
         }
         ConfigItemEvent::Removed(hash) => {
             let rule = state
                 .rules
-                .remove_rule(hash, &state.cron, &state.mqtt_client, &state.solar_events, &state.timers)
+                .remove_rule(
+                    hash,
+                    &state.cron,
+                    &state.mqtt_client,
+                    &state.solar_events,
+                    &state.timers,
+                    &state.deferred_triggers,
+                    &state.trigger_states,
+                )
                 .await?;
             if let Some(rule) = rule {
                 log::debug!(
@@ -35,6 +49,7 @@ pub async fn handle_rules_changes_event(event: ConfigItemEvent<Rule>, state: &mu
                     state.rules.get_filename(hash).unwrap_or(&"-".to_string())
                 );
             }
+            state.config_changed("rules").await;
         }
         ConfigItemEvent::NewDocument(filename_hash, path) => {
             state.rules.add_rule_file(filename_hash, path);
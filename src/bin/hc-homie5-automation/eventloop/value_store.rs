@@ -0,0 +1,8 @@
+use color_eyre::eyre::Result;
+use hc_homie5_automation::{app_state::AppState, rules::run_value_store_rules, value_store_events::ValueStoreEvent};
+
+pub async fn handle_value_store_event(event: ValueStoreEvent, state: &mut AppState) -> Result<bool> {
+    log::debug!("ValueStoreEvent: {:?}", event);
+    run_value_store_rules(&event, &state.as_rule_ctx()).await;
+    Ok(false)
+}
@@ -0,0 +1,8 @@
+use color_eyre::eyre::Result;
+use hc_homie5_automation::{app_state::AppState, rules::run_webhook_rules, webhook::WebhookEvent};
+
+pub async fn handle_webhook_event(event: WebhookEvent, state: &mut AppState) -> Result<bool> {
+    log::debug!("WebhookEvent: {} {}", event.request.method, event.request.path);
+    run_webhook_rules(event, &state.as_rule_ctx()).await;
+    Ok(false)
+}
@@ -18,6 +18,7 @@ async fn run_application() -> Result<()> {
         homie_ctrl_device_client,
         mqtt_client_handle,
         solar_events_handle,
+        webhook_listener_handle,
         mut state,
     ) = initialize_app().await?;
 
@@ -27,7 +28,14 @@ async fn run_application() -> Result<()> {
 
     run_event_loop(&mut event_multiplexer, &mut state).await?;
 
-    deinitialize_app(homie_discovery_client, homie_ctrl_device_client, mqtt_client_handle, solar_events_handle).await?;
+    deinitialize_app(
+        homie_discovery_client,
+        homie_ctrl_device_client,
+        mqtt_client_handle,
+        solar_events_handle,
+        webhook_listener_handle,
+    )
+    .await?;
 
     // make sure the channels stay open until the end...
     drop(event_multiplexer);
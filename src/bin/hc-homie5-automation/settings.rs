@@ -1,8 +1,9 @@
+use chrono_tz::Tz;
 use color_eyre::eyre::{self, eyre};
 use homie5::{HomieDomain, HomieID};
 use once_cell::sync::Lazy;
 use simple_kv_store::KubernetesResource;
-use std::{path::PathBuf, str::FromStr};
+use std::{net::SocketAddr, path::PathBuf, str::FromStr, time::Duration};
 
 use hc_homie5::settings::{self, HomieSettings};
 use hc_homie5_automation::virtual_devices::VirtualDeviceManagerConfig;
@@ -13,6 +14,8 @@ pub static ENV_PREFIX: Lazy<String> = Lazy::new(|| "HCACTL".to_string());
 pub static SETTINGS: Lazy<Settings> = Lazy::new(Settings::default);
 
 pub const CHANNEL_CAPACITY: usize = 65535;
+/// quiet period of discovery and configuration changes before startup/reload system events are emitted
+pub const SYSTEM_EVENT_SETTLE_TIME: Duration = Duration::from_secs(2);
 
 #[derive(Debug)]
 pub struct Settings {
@@ -35,8 +38,11 @@ pub struct AppSettings {
     pub virtual_devices_config: ConfigBackend,
     pub meta_config: ConfigBackend,
     pub lua_files_config: ConfigBackend,
+    pub calendars_config: CalendarsConfig,
     pub value_store_config: ValueStoreConfig,
     pub location: LocationConfig,
+    pub webhook_listener: WebhookListenerConfig,
+    pub timezone: TimezoneConfig,
 }
 
 /// - `latitude`: Latitude for the solar calculation.
@@ -93,6 +99,33 @@ impl FromStr for ConfigBackend {
     }
 }
 
+/// Backend calendars are loaded from, calendars are disabled unless a backend is configured
+#[derive(Debug)]
+pub enum CalendarsConfig {
+    Disabled,
+    Backend(ConfigBackend),
+}
+
+impl CalendarsConfig {
+    pub fn backend(&self) -> Option<&ConfigBackend> {
+        match self {
+            CalendarsConfig::Disabled => None,
+            CalendarsConfig::Backend(backend) => Some(backend),
+        }
+    }
+}
+
+impl TryFrom<String> for CalendarsConfig {
+    type Error = eyre::Report;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "disabled" => Ok(CalendarsConfig::Disabled),
+            _ => Ok(CalendarsConfig::Backend(s.parse()?)),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ValueStoreConfig {
     InMemory,
@@ -171,6 +204,72 @@ impl TryFrom<String> for LocationConfig {
         })
     }
 }
+#[derive(Debug, Clone)]
+pub enum WebhookListenerConfig {
+    Disabled,
+    Listen { addr: SocketAddr },
+}
+
+impl WebhookListenerConfig {
+    pub fn addr(&self) -> Option<SocketAddr> {
+        match self {
+            WebhookListenerConfig::Disabled => None,
+            WebhookListenerConfig::Listen { addr } => Some(*addr),
+        }
+    }
+}
+
+impl TryFrom<String> for WebhookListenerConfig {
+    type Error = eyre::Report;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "disabled" => Ok(WebhookListenerConfig::Disabled),
+            addr => match addr.parse() {
+                Ok(addr) => Ok(WebhookListenerConfig::Listen { addr }),
+                Err(_) => {
+                    println!("Error parsing webhook listener config: {}", s);
+                    Err(eyre!("Invalid format. Use 'disabled' or '<ip>:<port>' (e.g. '0.0.0.0:8080')"))
+                }
+            },
+        }
+    }
+}
+
+/// Timezone cron schedules are evaluated in
+#[derive(Debug, Clone)]
+pub enum TimezoneConfig {
+    Local,
+    Named(Tz),
+}
+
+impl TimezoneConfig {
+    pub fn tz(&self) -> Option<Tz> {
+        match self {
+            TimezoneConfig::Local => None,
+            TimezoneConfig::Named(tz) => Some(*tz),
+        }
+    }
+}
+
+impl TryFrom<String> for TimezoneConfig {
+    type Error = eyre::Report;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.trim() {
+            "" => Ok(TimezoneConfig::Named(Tz::UTC)),
+            "local" => Ok(TimezoneConfig::Local),
+            name => match name.parse() {
+                Ok(tz) => Ok(TimezoneConfig::Named(tz)),
+                Err(_) => {
+                    println!("Error parsing timezone config: {}", s);
+                    Err(eyre!("Invalid timezone. Use 'local' or an IANA timezone name (e.g. 'Europe/Vienna')"))
+                }
+            },
+        }
+    }
+}
+
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
@@ -202,6 +301,7 @@ impl Default for AppSettings {
                     path: PathBuf::from("./lua"),
                 },
             ),
+            calendars_config: settings::generic_setting(&ENV_PREFIX, "CALENDARS_CONFIG", CalendarsConfig::Disabled),
             value_store_config: settings::generic_setting(
                 &ENV_PREFIX,
                 "VALUE_STORE_CONFIG",
@@ -216,6 +316,8 @@ impl Default for AppSettings {
                     elevation: 0f64,
                 },
             ),
+            webhook_listener: settings::generic_setting(&ENV_PREFIX, "WEBHOOK_LISTEN", WebhookListenerConfig::Disabled),
+            timezone: settings::generic_setting(&ENV_PREFIX, "TIMEZONE", TimezoneConfig::Named(Tz::UTC)),
         }
     }
 }
@@ -0,0 +1,80 @@
+use std::{collections::HashMap, path::Path};
+
+use chrono::{DateTime, Local};
+use config_watcher::{config_item_watcher::ConfigItemEvent, ConfigItemHash};
+
+use super::Calendar;
+
+/// Keeps the calendars loaded by the calendar config watcher. Calendars are named after their file name
+/// without extensions (e.g. `holidays.ics` is `holidays`), unless a YAML calendar defines a `name`.
+#[derive(Default)]
+pub struct CalendarManager {
+    // Maps file hash (filename hash) -> calendar name derived from the filename
+    file_names: HashMap<u64, String>,
+    // Maps calendar name -> config item and calendar
+    calendars: HashMap<String, (ConfigItemHash, Calendar)>,
+}
+
+impl CalendarManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_event(&mut self, event: ConfigItemEvent<Calendar>) {
+        match event {
+            ConfigItemEvent::NewDocument(filename_hash, filename) => {
+                self.file_names
+                    .insert(filename_hash, Self::extract_calendar_name(&filename));
+            }
+            ConfigItemEvent::RemoveDocument(filename_hash) => {
+                self.file_names.remove(&filename_hash);
+            }
+            ConfigItemEvent::New(hash, calendar) => {
+                let Some(name) = calendar
+                    .name
+                    .clone()
+                    .or_else(|| self.file_names.get(&hash.filename_hash()).cloned())
+                else {
+                    log::warn!("Cannot determine the name of calendar {}", hash);
+                    return;
+                };
+                log::info!("Calendar loaded: {}", name);
+                self.calendars.insert(name, (hash, calendar));
+            }
+            ConfigItemEvent::Removed(hash) => {
+                // a changed calendar is only removed if it was not replaced already
+                self.calendars.retain(|name, (calendar_hash, _)| {
+                    if *calendar_hash == hash {
+                        log::info!("Calendar removed: {}", name);
+                    }
+                    *calendar_hash != hash
+                });
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Calendar> {
+        self.calendars.get(name).map(|(_, calendar)| calendar)
+    }
+
+    /// Returns true if the time is within the calendar. Calendars which are not loaded contain no time.
+    pub fn contains(&self, name: &str, now: DateTime<Local>) -> bool {
+        match self.get(name) {
+            Some(calendar) => calendar.contains(now),
+            None => {
+                log::warn!("Calendar [{}] is not loaded, it is treated as empty", name);
+                false
+            }
+        }
+    }
+
+    /// Utility: Extract the calendar name (basename up to the first `.`)
+    fn extract_calendar_name(path: &str) -> String {
+        Path::new(path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|name| name.split('.').next())
+            .unwrap_or(path)
+            .to_string()
+    }
+}
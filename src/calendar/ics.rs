@@ -0,0 +1,409 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone};
+use color_eyre::eyre::{eyre, Result};
+use rrule::{RRule, RRuleSet, Tz, Unvalidated};
+
+/// The recurrences computed for a day
+type DayOccurrences = (NaiveDate, Vec<DateTime<Tz>>);
+
+/// An event (`VEVENT`) of an iCalendar file, optionally repeated by a recurrence rule
+#[derive(Debug, Clone)]
+pub(crate) struct CalendarEvent {
+    start: DateTime<Tz>,
+    /// all day events start at midnight local time and last whole days
+    all_day: bool,
+    duration: TimeDelta,
+    recurrence: Option<RRuleSet>,
+    /// the recurrences which can contain a time of the cached day, they are only computed once per day
+    occurrences: Arc<Mutex<Option<DayOccurrences>>>,
+}
+
+impl CalendarEvent {
+    /// Returns true if the time is within the event or one of its recurrences
+    pub fn contains(&self, now: DateTime<Local>) -> bool {
+        let now = now.with_timezone(&Tz::LOCAL);
+        let (window_start, window_end) = self.start_window(now);
+        let started_within = |start: &DateTime<Tz>| window_start <= *start && *start <= window_end;
+
+        match &self.recurrence {
+            Some(recurrence) => {
+                let today = now.date_naive();
+                let mut occurrences = self.occurrences.lock().unwrap();
+                if occurrences.as_ref().is_none_or(|(day, _)| *day != today) {
+                    *occurrences = Some((today, self.day_occurrences(recurrence, today)));
+                }
+                occurrences
+                    .as_ref()
+                    .is_some_and(|(_, starts)| starts.iter().any(started_within))
+            }
+            None => started_within(&self.start),
+        }
+    }
+
+    /// The event contains the time if it (or a recurrence) started within this window
+    fn start_window(&self, now: DateTime<Tz>) -> (DateTime<Tz>, DateTime<Tz>) {
+        if self.all_day {
+            let days = self.duration.num_days().max(1);
+            let today = now.date_naive();
+            (start_of_day(today - TimeDelta::days(days - 1)), start_of_day(today))
+        } else {
+            (now - self.duration, now)
+        }
+    }
+
+    /// Collects the recurrences which can contain a time of the day, from the earliest start window of the
+    /// day up to the end of the day
+    fn day_occurrences(&self, recurrence: &RRuleSet, day: NaiveDate) -> Vec<DateTime<Tz>> {
+        let (first, _) = self.start_window(start_of_day(day));
+        let last = start_of_day(day + TimeDelta::days(1));
+        let result = recurrence.clone().after(first).before(last).all(u16::MAX);
+        if result.limited {
+            log::warn!(
+                "Recurrence of the calendar event starting at {} reached the iteration limit, occurrences on {} may be missing",
+                self.start,
+                day
+            );
+        }
+        result.dates
+    }
+}
+
+/// Parses the events of an iCalendar document. Events which cannot be parsed and cancelled events are skipped.
+pub(crate) fn parse_ics_events(doc: &str) -> Vec<CalendarEvent> {
+    let mut events = Vec::new();
+    // the nesting of components, properties are only collected for events (not e.g. their alarms)
+    let mut components: Vec<String> = Vec::new();
+    let mut properties: Vec<IcsProperty> = Vec::new();
+
+    for line in unfold_lines(doc) {
+        let Some(property) = IcsProperty::parse(&line) else {
+            continue;
+        };
+        match property.name.as_str() {
+            "BEGIN" => {
+                let component = property.value.to_ascii_uppercase();
+                if component == "VEVENT" {
+                    properties.clear();
+                }
+                components.push(component);
+            }
+            "END" => {
+                let component = components.pop();
+                if component.as_deref() == Some("VEVENT") {
+                    match parse_event(&properties) {
+                        Ok(Some(event)) => events.push(event),
+                        Ok(None) => {}
+                        Err(err) => log::warn!("Skipping calendar event: {}", err),
+                    }
+                }
+            }
+            _ if components.last().is_some_and(|component| component == "VEVENT") => properties.push(property),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Joins folded lines (continuation lines start with a space or tab)
+fn unfold_lines(doc: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in doc.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+struct IcsProperty {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl IcsProperty {
+    /// Parses a content line like `DTSTART;TZID=Europe/Vienna:20251224T180000`
+    fn parse(line: &str) -> Option<Self> {
+        let (name_params, value) = line.split_once(':')?;
+        let mut parts = name_params.split(';');
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_ascii_uppercase(), value.trim_matches('"').to_string()))
+            .collect();
+        Some(IcsProperty {
+            name,
+            params,
+            value: value.trim().to_string(),
+        })
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn parse_event(properties: &[IcsProperty]) -> Result<Option<CalendarEvent>> {
+    let property = |name: &str| properties.iter().find(|property| property.name == name);
+
+    if property("STATUS").is_some_and(|status| status.value.eq_ignore_ascii_case("CANCELLED")) {
+        return Ok(None);
+    }
+    let dtstart = property("DTSTART").ok_or_else(|| eyre!("missing DTSTART"))?;
+    let (start, all_day) = parse_datetime(&dtstart.value, dtstart)?;
+
+    let duration = if let Some(dtend) = property("DTEND") {
+        parse_datetime(&dtend.value, dtend)?.0 - start
+    } else if let Some(duration) = property("DURATION") {
+        parse_duration(&duration.value).ok_or_else(|| eyre!("invalid DURATION [{}]", duration.value))?
+    } else if all_day {
+        TimeDelta::days(1)
+    } else {
+        TimeDelta::zero()
+    };
+
+    let recurrence = match property("RRULE") {
+        Some(rrule) => {
+            let mut recurrence = rrule
+                .value
+                .parse::<RRule<Unvalidated>>()
+                .and_then(|rule| rule.build(start))
+                .map_err(|err| eyre!("invalid RRULE [{}]: {}", rrule.value, err))?;
+            for exdate in properties.iter().filter(|property| property.name == "EXDATE") {
+                for value in exdate.value.split(',') {
+                    recurrence = recurrence.exdate(parse_datetime(value, exdate)?.0);
+                }
+            }
+            Some(recurrence)
+        }
+        None => None,
+    };
+
+    Ok(Some(CalendarEvent {
+        start,
+        all_day,
+        duration,
+        recurrence,
+        occurrences: Arc::default(),
+    }))
+}
+
+/// Parses a date or date-time value, returns the time and whether it is a date (all day) value. Dates and
+/// date-times without timezone are local time.
+fn parse_datetime(value: &str, property: &IcsProperty) -> Result<(DateTime<Tz>, bool)> {
+    let value = value.trim();
+    if property
+        .param("VALUE")
+        .is_some_and(|kind| kind.eq_ignore_ascii_case("DATE"))
+        || value.len() == 8
+    {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| eyre!("invalid date [{}]", value))?;
+        return Ok((start_of_day(date), true));
+    }
+
+    let invalid = || eyre!("invalid date-time [{}]", value);
+    if let Some(utc) = value.strip_suffix('Z') {
+        let datetime = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+        return Ok((Tz::UTC.from_utc_datetime(&datetime), false));
+    }
+    let datetime = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+    let tz = match property.param("TZID") {
+        Some(tzid) => Tz::Tz(tzid.parse().map_err(|_| eyre!("unknown TZID [{}]", tzid))?),
+        None => Tz::LOCAL,
+    };
+    let datetime = tz.from_local_datetime(&datetime).earliest().ok_or_else(invalid)?;
+    Ok((datetime, false))
+}
+
+/// Returns midnight local time of the date, or the first valid time of the date if midnight was skipped by a
+/// daylight saving time change
+fn start_of_day(date: NaiveDate) -> DateTime<Tz> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    Tz::LOCAL
+        .from_local_datetime(&midnight)
+        .earliest()
+        .or_else(|| {
+            Tz::LOCAL
+                .from_local_datetime(&(midnight + TimeDelta::hours(1)))
+                .earliest()
+        })
+        .unwrap_or_else(|| Tz::UTC.from_utc_datetime(&midnight))
+}
+
+/// Parses a duration value like `P1D`, `PT1H30M` or `P2W`
+fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let value = value.strip_prefix('+').unwrap_or(value).strip_prefix('P')?;
+    let mut duration = TimeDelta::zero();
+    let mut number = String::new();
+    let mut time = false;
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => time = true,
+            unit => {
+                let amount: i64 = number.parse().ok()?;
+                number.clear();
+                duration += match (unit, time) {
+                    ('W', false) => TimeDelta::weeks(amount),
+                    ('D', false) => TimeDelta::days(amount),
+                    ('H', true) => TimeDelta::hours(amount),
+                    ('M', true) => TimeDelta::minutes(amount),
+                    ('S', true) => TimeDelta::seconds(amount),
+                    _ => return None,
+                };
+            }
+        }
+    }
+    number.is_empty().then_some(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    fn calendar(events: &str) -> String {
+        format!("BEGIN:VCALENDAR\nVERSION:2.0\n{}END:VCALENDAR\n", events)
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("P1D"), Some(TimeDelta::days(1)));
+        assert_eq!(parse_duration("PT1H30M"), Some(TimeDelta::minutes(90)));
+        assert_eq!(parse_duration("P2W"), Some(TimeDelta::weeks(2)));
+        assert_eq!(parse_duration("+P1DT2H"), Some(TimeDelta::hours(26)));
+        assert_eq!(parse_duration("PT45S"), Some(TimeDelta::seconds(45)));
+        // hours need the time designator, minutes without it are months
+        assert_eq!(parse_duration("P1H"), None);
+        assert_eq!(parse_duration("P1M"), None);
+        assert_eq!(parse_duration("P1"), None);
+        assert_eq!(parse_duration("1D"), None);
+    }
+
+    #[test]
+    fn parses_events() {
+        let events = parse_ics_events(&calendar(
+            "BEGIN:VEVENT\n\
+             SUMMARY:Christmas\n\
+             DTSTART;VALUE=DATE:20251224\n\
+             DTEND;VALUE=DATE:20251226\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             SUMMARY:A folded\n  summary\n\
+             DTSTART;TZID=Europe/Vienna:20251224T180000\n\
+             DURATION:PT2H\n\
+             BEGIN:VALARM\n\
+             DURATION:PT15M\n\
+             END:VALARM\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             DTSTART:20251231T230000Z\n\
+             STATUS:CANCELLED\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             DTSTART:2025-12-31\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             DTSTART:20260101T000000Z\n\
+             RRULE:FREQ=YEARLY\n\
+             END:VEVENT\n",
+        ));
+        // the cancelled event and the event with an invalid start are skipped
+        assert_eq!(events.len(), 3);
+
+        assert!(events[0].all_day);
+        assert_eq!(events[0].duration, TimeDelta::days(2));
+        assert_eq!(events[0].start.date_naive(), NaiveDate::from_ymd_opt(2025, 12, 24).unwrap());
+
+        // the duration of the alarm does not replace the one of the event
+        assert!(!events[1].all_day);
+        assert_eq!(events[1].duration, TimeDelta::hours(2));
+        assert_eq!(
+            events[1].start.naive_utc(),
+            NaiveDate::from_ymd_opt(2025, 12, 24)
+                .unwrap()
+                .and_hms_opt(17, 0, 0)
+                .unwrap()
+        );
+        assert!(events[1].recurrence.is_none());
+
+        assert_eq!(events[2].duration, TimeDelta::zero());
+        assert!(events[2].recurrence.is_some());
+    }
+
+    #[test]
+    fn unfolds_lines() {
+        assert_eq!(unfold_lines("A:1\n 2\n\t3\nB:4"), vec!["A:123".to_string(), "B:4".to_string()]);
+    }
+
+    #[test]
+    fn all_day_event_contains_whole_days() {
+        let events = parse_ics_events(&calendar(
+            "BEGIN:VEVENT\nDTSTART;VALUE=DATE:20251224\nDTEND;VALUE=DATE:20251226\nEND:VEVENT\n",
+        ));
+        let event = &events[0];
+        assert!(!event.contains(local(2025, 12, 23, 23, 59)));
+        assert!(event.contains(local(2025, 12, 24, 0, 0)));
+        assert!(event.contains(local(2025, 12, 25, 23, 59)));
+        assert!(!event.contains(local(2025, 12, 26, 0, 0)));
+    }
+
+    #[test]
+    fn timed_event_contains_its_duration() {
+        let events = parse_ics_events(&calendar("BEGIN:VEVENT\nDTSTART:20251224T180000\nDURATION:PT2H\nEND:VEVENT\n"));
+        let event = &events[0];
+        assert!(!event.contains(local(2025, 12, 24, 17, 59)));
+        assert!(event.contains(local(2025, 12, 24, 18, 0)));
+        assert!(event.contains(local(2025, 12, 24, 19, 59)));
+        assert!(!event.contains(local(2025, 12, 24, 20, 1)));
+    }
+
+    #[test]
+    fn recurring_event_contains_its_recurrences() {
+        let events = parse_ics_events(&calendar(
+            "BEGIN:VEVENT\n\
+             DTSTART:20250106T080000\n\
+             DURATION:PT1H\n\
+             RRULE:FREQ=WEEKLY;BYDAY=MO\n\
+             EXDATE:20250120T080000\n\
+             END:VEVENT\n",
+        ));
+        let event = &events[0];
+        assert!(event.contains(local(2025, 1, 13, 8, 30)));
+        assert!(!event.contains(local(2025, 1, 13, 9, 30)));
+        assert!(!event.contains(local(2025, 1, 14, 8, 30)));
+        // excluded recurrence
+        assert!(!event.contains(local(2025, 1, 20, 8, 30)));
+        assert!(event.contains(local(2025, 1, 27, 8, 0)));
+    }
+
+    #[test]
+    fn recurring_all_day_event_contains_whole_days() {
+        let events =
+            parse_ics_events(&calendar("BEGIN:VEVENT\nDTSTART;VALUE=DATE:20200101\nRRULE:FREQ=YEARLY\nEND:VEVENT\n"));
+        let event = &events[0];
+        assert!(!event.contains(local(2025, 12, 31, 23, 59)));
+        assert!(event.contains(local(2026, 1, 1, 0, 0)));
+        assert!(event.contains(local(2026, 1, 1, 23, 59)));
+        assert!(!event.contains(local(2026, 1, 2, 0, 0)));
+    }
+
+    #[test]
+    fn old_hourly_recurrence_is_contained() {
+        let events = parse_ics_events(&calendar(
+            "BEGIN:VEVENT\nDTSTART:20150101T000000\nDURATION:PT30M\nRRULE:FREQ=HOURLY\nEND:VEVENT\n",
+        ));
+        let event = &events[0];
+        assert!(event.contains(local(2026, 3, 10, 10, 15)));
+        assert!(!event.contains(local(2026, 3, 10, 10, 45)));
+        assert!(event.contains(local(2026, 3, 10, 11, 0)));
+    }
+}
@@ -0,0 +1,6 @@
+mod calendar_manager;
+mod ics;
+mod model;
+
+pub use calendar_manager::CalendarManager;
+pub use model::*;
@@ -0,0 +1,236 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use color_eyre::eyre::{self, eyre};
+use serde::Deserialize;
+
+use super::ics::{parse_ics_events, CalendarEvent};
+
+/// A day with or without year, e.g. `2025-12-24` or `12-24` for the 24th of december of every year
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum DateSpec {
+    Date(NaiveDate),
+    MonthDay { month: u32, day: u32 },
+}
+
+impl FromStr for DateSpec {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Ok(DateSpec::Date(date));
+        }
+        // checked against a leap year to accept `02-29`
+        NaiveDate::parse_from_str(&format!("2000-{}", s), "%Y-%m-%d")
+            .map(|date| DateSpec::MonthDay {
+                month: date.month(),
+                day: date.day(),
+            })
+            .map_err(|_| eyre!("Invalid date [{}]. Use 'YYYY-MM-DD' or 'MM-DD'", s))
+    }
+}
+
+impl TryFrom<String> for DateSpec {
+    type Error = eyre::Report;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+enum DateRangeDef {
+    Day(DateSpec),
+    Range { from: DateSpec, to: DateSpec },
+}
+
+/// A single day or a range of days (both ends included). Ranges without year repeat every year and may span
+/// the turn of the year (e.g. `12-20` to `01-06`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "DateRangeDef")]
+pub struct DateRange {
+    from: DateSpec,
+    to: DateSpec,
+}
+
+impl TryFrom<DateRangeDef> for DateRange {
+    type Error = eyre::Report;
+
+    fn try_from(value: DateRangeDef) -> Result<Self, Self::Error> {
+        match value {
+            DateRangeDef::Day(day) => Ok(DateRange { from: day, to: day }),
+            DateRangeDef::Range { from, to } => match (from, to) {
+                (DateSpec::Date(_), DateSpec::Date(_)) | (DateSpec::MonthDay { .. }, DateSpec::MonthDay { .. }) => {
+                    Ok(DateRange { from, to })
+                }
+                _ => Err(eyre!("Date range from and to either both need a year or both none")),
+            },
+        }
+    }
+}
+
+impl DateRange {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        match (self.from, self.to) {
+            (DateSpec::Date(from), DateSpec::Date(to)) => from <= date && date <= to,
+            (
+                DateSpec::MonthDay {
+                    month: from_month,
+                    day: from_day,
+                },
+                DateSpec::MonthDay {
+                    month: to_month,
+                    day: to_day,
+                },
+            ) => {
+                let day = (date.month(), date.day());
+                let (from, to) = ((from_month, from_day), (to_month, to_day));
+                if from <= to {
+                    from <= day && day <= to
+                } else {
+                    from <= day || day <= to
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CalendarSpec {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    dates: Vec<DateRange>,
+}
+
+/// A named set of days and events used by time while-conditions, loaded from an iCalendar (`.ics`) or a
+/// YAML file
+#[derive(Debug, Clone)]
+pub struct Calendar {
+    /// name defined in a YAML calendar, other calendars are named after their file
+    pub name: Option<String>,
+    dates: Vec<DateRange>,
+    events: Vec<CalendarEvent>,
+}
+
+impl Calendar {
+    /// Parses an iCalendar document (starting with `BEGIN:VCALENDAR`) or a YAML calendar
+    pub fn parse(doc: &str) -> eyre::Result<Self> {
+        if doc.trim_start().starts_with("BEGIN:VCALENDAR") {
+            Ok(Calendar {
+                name: None,
+                dates: Vec::new(),
+                events: parse_ics_events(doc),
+            })
+        } else {
+            let spec: CalendarSpec = serde_yaml_ng::from_str(doc)?;
+            Ok(Calendar {
+                name: spec.name,
+                dates: spec.dates,
+                events: Vec::new(),
+            })
+        }
+    }
+
+    /// Returns true if the time is on one of the days or within one of the events of the calendar
+    pub fn contains(&self, now: DateTime<Local>) -> bool {
+        self.dates.iter().any(|range| range.contains(now.date_naive()))
+            || self.events.iter().any(|event| event.contains(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn range(yaml: &str) -> DateRange {
+        serde_yaml_ng::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn parses_date_specs() {
+        assert_eq!("2025-12-24".parse::<DateSpec>().unwrap(), DateSpec::Date(date(2025, 12, 24)));
+        assert_eq!("12-24".parse::<DateSpec>().unwrap(), DateSpec::MonthDay { month: 12, day: 24 });
+        assert_eq!("02-29".parse::<DateSpec>().unwrap(), DateSpec::MonthDay { month: 2, day: 29 });
+        assert!("02-30".parse::<DateSpec>().is_err());
+        assert!("2025-02-29".parse::<DateSpec>().is_err());
+        assert!("24.12.".parse::<DateSpec>().is_err());
+    }
+
+    #[test]
+    fn single_day_contains_only_that_day() {
+        let day = range("2025-12-24");
+        assert!(day.contains(date(2025, 12, 24)));
+        assert!(!day.contains(date(2025, 12, 23)));
+        assert!(!day.contains(date(2026, 12, 24)));
+
+        let every_year = range("12-24");
+        assert!(every_year.contains(date(2025, 12, 24)));
+        assert!(every_year.contains(date(2030, 12, 24)));
+        assert!(!every_year.contains(date(2025, 12, 25)));
+    }
+
+    #[test]
+    fn leap_day_is_only_contained_in_leap_years() {
+        let leap_day = range("02-29");
+        assert!(leap_day.contains(date(2024, 2, 29)));
+        assert!(!leap_day.contains(date(2025, 2, 28)));
+        assert!(!leap_day.contains(date(2025, 3, 1)));
+    }
+
+    #[test]
+    fn range_with_year_contains_days_between() {
+        let holidays = range("{ from: 2025-12-22, to: 2026-01-06 }");
+        assert!(!holidays.contains(date(2025, 12, 21)));
+        assert!(holidays.contains(date(2025, 12, 22)));
+        assert!(holidays.contains(date(2026, 1, 1)));
+        assert!(holidays.contains(date(2026, 1, 6)));
+        assert!(!holidays.contains(date(2026, 1, 7)));
+        assert!(!holidays.contains(date(2026, 12, 24)));
+    }
+
+    #[test]
+    fn range_without_year_wraps_over_the_new_year() {
+        let holidays = range("{ from: 12-20, to: 01-06 }");
+        assert!(!holidays.contains(date(2025, 12, 19)));
+        assert!(holidays.contains(date(2025, 12, 20)));
+        assert!(holidays.contains(date(2025, 12, 31)));
+        assert!(holidays.contains(date(2026, 1, 1)));
+        assert!(holidays.contains(date(2026, 1, 6)));
+        assert!(!holidays.contains(date(2026, 1, 7)));
+        assert!(!holidays.contains(date(2026, 7, 1)));
+
+        let summer = range("{ from: 07-01, to: 08-31 }");
+        assert!(summer.contains(date(2025, 7, 1)));
+        assert!(summer.contains(date(2025, 8, 31)));
+        assert!(!summer.contains(date(2025, 9, 1)));
+        assert!(!summer.contains(date(2025, 1, 1)));
+    }
+
+    #[test]
+    fn range_ends_need_the_same_format() {
+        assert!(serde_yaml_ng::from_str::<DateRange>("{ from: 2025-12-20, to: 01-06 }").is_err());
+    }
+
+    #[test]
+    fn yaml_calendar_contains_its_dates() {
+        let calendar =
+            Calendar::parse("name: holidays\ndates:\n  - 2025-10-27\n  - from: 12-24\n    to: 12-26\n").unwrap();
+        assert_eq!(calendar.name.as_deref(), Some("holidays"));
+        let noon = |date: NaiveDate| Local.from_local_datetime(&date.and_hms_opt(12, 0, 0).unwrap()).unwrap();
+        assert!(calendar.contains(noon(date(2025, 10, 27))));
+        assert!(calendar.contains(noon(date(2027, 12, 25))));
+        assert!(!calendar.contains(noon(date(2025, 10, 28))));
+    }
+}
@@ -1,17 +1,112 @@
-use chrono::{DateTime, Local, Utc};
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use chrono_tz::Tz;
 use config_watcher::ConfigItemHash;
 use cron::Schedule;
+use serde::Deserialize;
 use std::{
     collections::HashMap,
     str::FromStr,
     sync::{Arc, Mutex},
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 use tokio::{
     sync::mpsc::{self, Receiver, Sender},
     task::JoinHandle,
 };
 
+use crate::utils::random_jitter;
+
+/// A parsed cron schedule. Besides the 7 field (sec, min, hour, day of month, month, day of week, year) and
+/// 6 field (without year) format, standard 5 field crontab expressions and nicknames like `@daily` are accepted.
+///
+/// Crontab expressions restricting both the day of month and the day of week fire when either of them matches,
+/// they are kept as two schedules (one per day field) and the earlier execution of both is used.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct CronSchedule(Vec<Schedule>);
+
+impl FromStr for CronSchedule {
+    type Err = cron::error::Error;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if let [minute, hour, day_of_month, month, day_of_week] = fields[..] {
+            // crontab format: add the seconds field and convert the day of week numbering
+            let day_of_week = crontab_day_of_week(day_of_week);
+            let crontab = |day_of_month: &str, day_of_week: &str| {
+                Schedule::from_str(&format!("0 {} {} {} {} {}", minute, hour, day_of_month, month, day_of_week))
+            };
+            if day_of_month != "*" && day_of_week != "*" {
+                Ok(CronSchedule(vec![crontab(day_of_month, "*")?, crontab("*", &day_of_week)?]))
+            } else {
+                crontab(day_of_month, &day_of_week).map(|schedule| CronSchedule(vec![schedule]))
+            }
+        } else {
+            Schedule::from_str(expression.trim()).map(|schedule| CronSchedule(vec![schedule]))
+        }
+    }
+}
+
+impl TryFrom<String> for CronSchedule {
+    type Error = cron::error::Error;
+
+    fn try_from(expression: String) -> Result<Self, Self::Error> {
+        expression.parse()
+    }
+}
+
+/// Converts a crontab day of week field (0-7, sunday is 0 and 7) to the numbering of the 7 field format
+/// (1-7, sunday is 1). Numeric ranges are expanded to lists as e.g. `5-7` (friday to sunday) has no
+/// equivalent range. Names and `*` are the same in both formats.
+fn crontab_day_of_week(field: &str) -> String {
+    field
+        .split(',')
+        .map(|item| {
+            let (range, step) = item
+                .split_once('/')
+                .map_or((item, None), |(range, step)| (range, Some(step)));
+            let bounds = match range.split_once('-') {
+                Some((start, end)) => start.parse::<u32>().ok().zip(end.parse::<u32>().ok()),
+                None => range
+                    .parse::<u32>()
+                    .ok()
+                    .map(|day| (day, if step.is_some() { 7 } else { day })),
+            };
+            match (bounds.filter(|(start, end)| start <= end && *end <= 7), step.unwrap_or("1").parse::<usize>()) {
+                (Some((start, end)), Ok(step)) if step > 0 => (start..=end)
+                    .step_by(step)
+                    .map(|day| (day % 7 + 1).to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                _ => item.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Returns the next execution of the schedule after `after`, evaluated in the timezone (local time if `None`)
+fn next_execution(schedule: &CronSchedule, timezone: Option<Tz>, after: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+    schedule
+        .0
+        .iter()
+        .filter_map(|schedule| next_schedule_execution(schedule, timezone, after))
+        .min()
+}
+
+fn next_schedule_execution(schedule: &Schedule, timezone: Option<Tz>, after: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match timezone {
+        Some(timezone) => schedule
+            .after(&after.with_timezone(&timezone))
+            .next()
+            .map(|next| next.with_timezone(&Utc)),
+        None => schedule
+            .after(&after.with_timezone(&Local))
+            .next()
+            .map(|next| next.with_timezone(&Utc)),
+    }
+}
+
 #[derive(Debug)]
 pub struct ScheduledCron {
     #[allow(dead_code)]
@@ -26,28 +121,41 @@ pub struct CronEvent {
     pub id: String,
     pub rule_hash: ConfigItemHash,
     pub trigger_index: usize,
+    /// the random offset the event fired with if the trigger has a jitter
+    pub jitter_offset: Option<TimeDelta>,
 }
 
 #[derive(Debug, Clone)]
 pub struct CronManager {
     cron_schedules: Arc<Mutex<HashMap<String, ScheduledCron>>>,
     sender: Sender<CronEvent>,
+    /// timezone schedules are evaluated in unless a trigger overrides it, local time if `None`
+    timezone: Option<Tz>,
 }
 
 impl CronManager {
-    pub fn new() -> (Self, Receiver<CronEvent>) {
+    pub fn new(timezone: Option<Tz>) -> (Self, Receiver<CronEvent>) {
         let (sender, receiver) = mpsc::channel(1024);
         (
             Self {
                 cron_schedules: Arc::new(Mutex::new(HashMap::new())),
                 sender,
+                timezone,
             },
             receiver,
         )
     }
 
-    /// Creates a new timer
-    pub fn schedule_cron(&self, rule_hash: ConfigItemHash, trigger_index: usize, cron_schedule: &str) {
+    /// Creates a new timer, every firing is randomly offset within ±`jitter`. The schedule is evaluated in
+    /// the given timezone or the default timezone of the manager.
+    pub fn schedule_cron(
+        &self,
+        rule_hash: ConfigItemHash,
+        trigger_index: usize,
+        cron_schedule: &CronSchedule,
+        timezone: Option<Tz>,
+        jitter: Option<Duration>,
+    ) {
         let id = format!("{}-{}", rule_hash, trigger_index);
         // cancel existing timer for the id if it exists
         self.cancel_cron_schedule(&id);
@@ -55,32 +163,40 @@ impl CronManager {
         let sender = self.sender.clone();
 
         let id_task = id.clone();
-        let task_cron_schedule = cron_schedule.to_string();
+        let schedule = cron_schedule.clone();
+        let timezone = timezone.or(self.timezone);
 
         // Spawn a new task for the timer
         let handle = tokio::spawn(async move {
-            let Ok(schedule) = Schedule::from_str(&task_cron_schedule) else {
-                log::error!("Invalid cron expression: [{}] - 7 fields required, like [* * * * * * *] (sec, min, hour, day of month, month, day of week, year)", task_cron_schedule);
-                return;
-            };
+            let mut last: DateTime<Utc> = SystemTime::now().into();
 
             // Iterate over the schedule and send events
-            for next in schedule.upcoming(Utc) {
+            while let Some(next) = next_execution(&schedule, timezone, &last) {
+                last = next;
+                let jitter_offset = jitter.map(random_jitter);
+                let next = next + jitter_offset.unwrap_or_default();
                 // Calculate the delay
                 let now: DateTime<Utc> = SystemTime::now().into();
-                let delay = (next - now)
-                    .to_std()
-                    .unwrap_or_else(|_| std::time::Duration::from_secs(0));
+                let delay = (next - now).to_std().unwrap_or_else(|_| Duration::from_secs(0));
 
                 log::debug!("{} - next execution at: {}", id_task, next.with_timezone(&Local));
                 tokio::time::sleep(delay).await;
 
+                if let Some(jitter_offset) = jitter_offset {
+                    log::info!(
+                        "{} - executing with jitter offset {}s",
+                        id_task,
+                        jitter_offset.num_milliseconds() as f64 / 1000.0
+                    );
+                }
+
                 // Send the event via the channel
                 if let Err(err) = sender
                     .send(CronEvent {
                         id: id_task.clone(),
                         rule_hash,
                         trigger_index,
+                        jitter_offset,
                     })
                     .await
                 {
@@ -0,0 +1,146 @@
+use config_watcher::ConfigItemHash;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc::{self, Receiver, Sender},
+    task::JoinHandle,
+};
+
+use crate::rules::RuleTriggerEvent;
+
+#[derive(Debug)]
+pub struct DeferredTrigger {
+    pub rule_hash: ConfigItemHash,
+    pub seq: u64,
+    pub handle: JoinHandle<()>,
+}
+
+/// Emitted once a deferred trigger (e.g. a `for` hold period) has elapsed.
+#[derive(Debug, Clone)]
+pub struct DeferredTriggerEvent {
+    pub id: String,
+    pub seq: u64,
+    pub rule_hash: ConfigItemHash,
+    pub trigger_index: usize,
+    pub trigger_event: Box<RuleTriggerEvent<'static>>,
+}
+
+/// Manages rule triggers whose firing is deferred to a later point in time.
+///
+/// Every deferred trigger is identified by an id, scheduling the same id again replaces the
+/// pending one. Events are only valid if they are confirmed via [`DeferredTriggerManager::complete`],
+/// this filters out events which were already sent when their trigger got cancelled or replaced.
+#[derive(Debug, Clone)]
+pub struct DeferredTriggerManager {
+    triggers: Arc<Mutex<HashMap<String, DeferredTrigger>>>,
+    next_seq: Arc<AtomicU64>,
+    sender: Sender<DeferredTriggerEvent>,
+}
+
+impl DeferredTriggerManager {
+    pub fn new() -> (Self, Receiver<DeferredTriggerEvent>) {
+        let (sender, receiver) = mpsc::channel(1024);
+        (
+            Self {
+                triggers: Arc::new(Mutex::new(HashMap::new())),
+                next_seq: Arc::new(AtomicU64::new(0)),
+                sender,
+            },
+            receiver,
+        )
+    }
+
+    /// Schedules a trigger to fire after `delay`
+    pub fn schedule(
+        &self,
+        rule_hash: ConfigItemHash,
+        trigger_index: usize,
+        id: String,
+        delay: Duration,
+        trigger_event: &RuleTriggerEvent<'_>,
+    ) {
+        // cancel existing deferred trigger for the id if it exists
+        self.cancel(&id);
+
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let sender = self.sender.clone();
+        let id_task = id.clone();
+        let trigger_event = Box::new(trigger_event.to_owned());
+
+        let handle = tokio::spawn(async move {
+            log::debug!("Deferred trigger {} scheduled in {:?}", id_task, delay);
+            tokio::time::sleep(delay).await;
+
+            if let Err(err) = sender
+                .send(DeferredTriggerEvent {
+                    id: id_task.clone(),
+                    seq,
+                    rule_hash,
+                    trigger_index,
+                    trigger_event,
+                })
+                .await
+            {
+                log::warn!("Error sending deferred trigger: [{}] - {}", id_task, err);
+            }
+        });
+
+        self.triggers
+            .lock()
+            .unwrap()
+            .insert(id, DeferredTrigger { rule_hash, seq, handle });
+    }
+
+    /// Returns true if a trigger with the given id is still pending
+    pub fn is_scheduled(&self, id: &str) -> bool {
+        self.triggers.lock().unwrap().contains_key(id)
+    }
+
+    /// Marks the deferred trigger belonging to the event as done.
+    /// Returns false if the trigger was cancelled or replaced in the meantime.
+    pub fn complete(&self, event: &DeferredTriggerEvent) -> bool {
+        let mut triggers = self.triggers.lock().unwrap();
+        if triggers.get(&event.id).is_some_and(|trigger| trigger.seq == event.seq) {
+            triggers.remove(&event.id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Cancels a deferred trigger by ID
+    pub fn cancel(&self, id: &str) {
+        let mut triggers = self.triggers.lock().unwrap();
+        if let Some(trigger) = triggers.remove(id) {
+            trigger.handle.abort();
+            log::debug!("Deferred trigger {} cancelled.", id);
+        }
+    }
+
+    pub fn remove_for_rule(&self, rule_hash: ConfigItemHash) {
+        let mut triggers = self.triggers.lock().unwrap();
+        triggers.retain(|id, trigger| {
+            if trigger.rule_hash == rule_hash {
+                trigger.handle.abort();
+                log::debug!("Deferred trigger {} cancelled.", id);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    pub fn clear(&self) {
+        log::debug!("Removing all deferred triggers");
+        let mut triggers = self.triggers.lock().unwrap();
+        for (_, trigger) in triggers.drain() {
+            trigger.handle.abort();
+        }
+    }
+}
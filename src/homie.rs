@@ -1 +1,27 @@
+use hc_homie5::client::HomieMQTTClient;
 pub use hc_homie5::controller::HomieControllerClient;
+use homie5::{Homie5ControllerProtocol, HomieDomain};
+
+/// Publishes homie broadcast messages (`<homie-domain>/5/$broadcast/<subtopic>`)
+#[derive(Clone)]
+pub struct HomieBroadcaster {
+    protocol: Homie5ControllerProtocol,
+    homie_client: HomieMQTTClient,
+    homie_domain: HomieDomain,
+}
+
+impl HomieBroadcaster {
+    pub fn new(protocol: Homie5ControllerProtocol, homie_client: HomieMQTTClient, homie_domain: HomieDomain) -> Self {
+        Self {
+            protocol,
+            homie_client,
+            homie_domain,
+        }
+    }
+
+    pub async fn broadcast(&self, subtopic: &str, data: impl Into<String>) -> Result<(), rumqttc::ClientError> {
+        self.homie_client
+            .homie_publish(self.protocol.send_broadcast(&self.homie_domain, subtopic, data))
+            .await
+    }
+}
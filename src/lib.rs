@@ -1,6 +1,8 @@
 pub mod app_state;
+pub mod calendar;
 pub mod cfg_files_tracker;
 pub mod cron_manager;
+pub mod deferred_trigger_manager;
 pub mod device_manager;
 pub mod homie;
 pub mod lua_runtime;
@@ -9,7 +11,11 @@ pub mod mqtt_client;
 pub mod rule_manager;
 pub mod rules;
 pub mod solar_events;
+pub mod system_events;
 pub mod timer_manager;
+pub mod trigger_state;
 pub mod unwrap_or_exit;
 pub mod utils;
+pub mod value_store_events;
 pub mod virtual_devices;
+pub mod webhook;
@@ -1,5 +1,8 @@
-use crate::{lua_runtime::LuaPropertyRef, rules::RuleTriggerEvent};
-use mlua::{IntoLua, UserData};
+use crate::{
+    lua_runtime::{LuaDeviceRef, LuaPropertyRef},
+    rules::RuleTriggerEvent,
+};
+use mlua::{ExternalResult, IntoLua, LuaSerdeExt, UserData};
 
 use super::LuaHomieValue;
 
@@ -14,6 +17,43 @@ impl UserData for LuaEvent {
             let res = this.event.property_ref().map(|value| LuaPropertyRef(value.clone()));
             Ok(res)
         });
+        fields.add_field_method_get("device", |_, this| {
+            let res = this.event.device_ref().map(|value| LuaDeviceRef(value.clone()));
+            Ok(res)
+        });
+        fields.add_field_method_get("description", |lua, this| {
+            let res = if let Some(desc) = this.event.device_description() {
+                let ser: serde_json::Value = serde_json::to_value(desc).into_lua_err()?;
+                lua.to_value(&ser)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("from_state", |lua, this| {
+            let res = if let Some((from, _)) = this.event.device_state() {
+                from.as_str().into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("to_state", |lua, this| {
+            let res = if let Some((_, to)) = this.event.device_state() {
+                to.as_str().into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("alert_id", |lua, this| {
+            let res = if let Some(value) = this.event.alert_id().map(|v| v.to_string()) {
+                value.into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
         fields.add_field_method_get("on_set_value", |lua, this| {
             let res = if let Some(value) = this.event.on_set_value().map(|v| v.to_string()) {
                 value.into_lua(lua)?
@@ -23,17 +63,40 @@ impl UserData for LuaEvent {
             Ok(res)
         });
         fields.add_field_method_get("value", |lua, this| {
-            let res = match &this.event {
+            let res = match this.event.source_event() {
                 RuleTriggerEvent::PropertyChanged { to, .. } => LuaHomieValue(to.clone().into_owned()).into_lua(lua)?,
                 RuleTriggerEvent::PropertyTriggered { value, .. } => {
                     LuaHomieValue(value.clone().into_owned()).into_lua(lua)?
                 }
 
+                RuleTriggerEvent::PropertyStale { last_value, .. } => match last_value.as_ref() {
+                    Some(value) => LuaHomieValue(value.clone()).into_lua(lua)?,
+                    None => mlua::Value::Nil,
+                },
+                RuleTriggerEvent::DeviceStateChanged { to, .. } => lua.create_string(to.as_str())?.into_lua(lua)?,
+                RuleTriggerEvent::DeviceAdded { .. } => mlua::Value::Nil,
+                RuleTriggerEvent::DeviceRemoved { .. } => mlua::Value::Nil,
+                RuleTriggerEvent::DeviceAlertRaised { message, .. } => lua.create_string(&**message)?.into_lua(lua)?,
+                RuleTriggerEvent::DeviceAlertCleared { .. } => mlua::Value::Nil,
                 RuleTriggerEvent::Timer(_) => mlua::Value::Nil,
                 RuleTriggerEvent::Cron(_) => mlua::Value::Nil,
-                RuleTriggerEvent::Mqtt(mqtt_event) => lua.create_string(&mqtt_event.payload)?.into_lua(lua)?,
+                RuleTriggerEvent::Mqtt {
+                    extracted: Some(extracted),
+                    ..
+                } => lua.to_value(extracted.as_ref())?,
+                RuleTriggerEvent::Mqtt { event, .. } => lua.create_string(&event.payload)?.into_lua(lua)?,
                 RuleTriggerEvent::OnSet { value, .. } => lua.create_string(&**value)?.into_lua(lua)?,
                 RuleTriggerEvent::Solar(_) => mlua::Value::Nil,
+                RuleTriggerEvent::System(system_event) => {
+                    lua.create_string(system_event.kind.as_str())?.into_lua(lua)?
+                }
+                RuleTriggerEvent::ValueStore(value_store_event) => match &value_store_event.value {
+                    Some(value) => lua.to_value(value)?,
+                    None => mlua::Value::Nil,
+                },
+                RuleTriggerEvent::Webhook(request) => lua.create_string(&request.body)?.into_lua(lua)?,
+                RuleTriggerEvent::Broadcast { data, .. } => lua.create_string(&**data)?.into_lua(lua)?,
+                RuleTriggerEvent::Counted { .. } | RuleTriggerEvent::AllOf { .. } => mlua::Value::Nil,
             };
             Ok(res)
         });
@@ -45,6 +108,107 @@ impl UserData for LuaEvent {
             };
             Ok(res)
         });
+        fields.add_field_method_get("last_received", |lua, this| {
+            let res = if let Some(value) = this.event.last_received().map(|v| v.to_rfc3339()) {
+                value.into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("detail", |lua, this| {
+            let res = if let Some(value) = this.event.system_event().and_then(|e| e.detail.clone()) {
+                value.into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("method", |lua, this| {
+            let res = if let Some(request) = this.event.webhook_request() {
+                request.method.as_str().into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("path", |lua, this| {
+            let res = if let Some(request) = this.event.webhook_request() {
+                request.path.as_str().into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("headers", |lua, this| {
+            let res = if let Some(request) = this.event.webhook_request() {
+                lua.create_table_from(request.headers.iter().map(|(k, v)| (k.as_str(), v.as_str())))?
+                    .into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("query", |lua, this| {
+            let res = if let Some(request) = this.event.webhook_request() {
+                lua.create_table_from(request.query.iter().map(|(k, v)| (k.as_str(), v.as_str())))?
+                    .into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("subtopic", |lua, this| {
+            let res = if let Some(value) = this.event.broadcast_subtopic() {
+                value.into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("key", |lua, this| {
+            let res = if let Some(event) = this.event.value_store_event() {
+                event.key.as_str().into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("count", |lua, this| {
+            let res = if let Some(value) = this.event.count() {
+                value.into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("window", |lua, this| {
+            let res = if let Some(value) = this.event.window().map(|v| v.as_secs_f64()) {
+                value.into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("events", |lua, this| {
+            let res = if let Some(events) = this.event.all_of_events() {
+                lua.create_sequence_from(events.iter().map(|event| LuaEvent {
+                    event: event.to_owned(),
+                }))?
+                .into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
+        fields.add_field_method_get("jitter", |lua, this| {
+            let res = if let Some(value) = this.event.jitter_offset() {
+                (value.num_milliseconds() as f64 / 1000.0).into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
         fields.add_field_method_get("timer_id", |lua, this| {
             let res = if let Some(value) = this.event.timer_id().map(|v| v.to_string()) {
                 value.into_lua(lua)?
@@ -61,6 +225,14 @@ impl UserData for LuaEvent {
             };
             Ok(res)
         });
+        fields.add_field_method_get("payload", |lua, this| {
+            let res = if let Some(value) = this.event.mqtt_payload() {
+                lua.create_string(value)?.into_lua(lua)?
+            } else {
+                mlua::Value::Nil
+            };
+            Ok(res)
+        });
         fields.add_field_method_get("mqtt_retain", |lua, this| {
             let res = if let Some(value) = this.event.mqtt_retain() {
                 value.into_lua(lua)?
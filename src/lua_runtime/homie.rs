@@ -1,9 +1,10 @@
 use super::{LuaDeviceRef, LuaHomieValue, LuaPropertyRef};
-use crate::device_manager::DeviceManager;
+use crate::{device_manager::DeviceManager, homie::HomieBroadcaster};
 use mlua::{ExternalResult, LuaSerdeExt, UserData};
 
 pub struct LuaHomie {
     pub dm: DeviceManager,
+    pub broadcaster: HomieBroadcaster,
 }
 
 impl UserData for LuaHomie {
@@ -16,6 +17,13 @@ impl UserData for LuaHomie {
                 homie.dm.set_command(&prop.0, &value.0).await.into_lua_err()
             },
         );
+        methods.add_async_method("broadcast", |_, homie, (subtopic, data): (mlua::String, mlua::String)| async move {
+            homie
+                .broadcaster
+                .broadcast(&subtopic.to_str()?, data.to_str()?.to_string())
+                .await
+                .into_lua_err()
+        });
         methods.add_async_method("get_value", |_, homie, subject: mlua::Value| async move {
             let prop = LuaPropertyRef::try_from(subject).into_lua_err()?;
             let devices = homie.dm.read().await;
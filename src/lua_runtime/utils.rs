@@ -67,7 +67,7 @@ impl UserData for LuaUtils {
         });
         methods.add_async_method(
             "mqtt_publish",
-            |_, this, (topic, payload, qos, retained): (String, String, Option<i64>, Option<bool>)| async move {
+            |_, this, (topic, payload, qos, retained): (String, mlua::String, Option<i64>, Option<bool>)| async move {
                 let qos = match qos.unwrap_or(0) {
                     0 => QoS::AtMostOnce,
                     1 => QoS::AtLeastOnce,
@@ -76,7 +76,7 @@ impl UserData for LuaUtils {
                 };
                 let retained = retained.unwrap_or(false);
                 this.mqtt_client
-                    .publish(topic, qos, retained, payload)
+                    .publish(topic, qos, retained, payload.as_bytes().to_vec())
                     .await
                     .into_lua_err()?;
                 Ok(())
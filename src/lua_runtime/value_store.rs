@@ -1,8 +1,11 @@
 use mlua::{ExternalResult, LuaSerdeExt, ObjectLike, UserData};
 use simple_kv_store::{normalize_key, KeyValueStore};
 
+use crate::value_store_events::ValueStoreNotifier;
+
 pub struct LuaValueStore {
     pub store: KeyValueStore,
+    pub events: ValueStoreNotifier,
 }
 
 impl UserData for LuaValueStore {
@@ -26,7 +29,9 @@ impl UserData for LuaValueStore {
                 lua.from_value::<serde_json::Value>(value)?
             };
 
-            vs.store.set(&n_key, &serialized_value).await.into_lua_err()
+            vs.store.set(&n_key, &serialized_value).await.into_lua_err()?;
+            vs.events.notify(n_key, Some(serialized_value));
+            Ok(())
         });
         methods.add_async_method("get", |lua, homie, key: mlua::String| async move {
             let n_key = normalize_key(&key.to_str()?);
@@ -40,6 +45,7 @@ impl UserData for LuaValueStore {
         methods.add_async_method("delete", |_, homie, key: mlua::String| async move {
             let n_key = normalize_key(&key.to_str()?);
             homie.store.delete(&n_key).await.into_lua_err()?;
+            homie.events.notify(n_key, None);
             Ok(())
         });
     }
@@ -23,12 +23,19 @@ pub enum MqttClientError {
 #[derive(Clone, Debug)]
 pub struct MqttPublishEvent {
     pub topic: String,
-    pub payload: String,
+    pub payload: Vec<u8>,
     pub duplicate: bool,
     pub retain: bool,
     pub qos: QoS,
 }
 
+impl MqttPublishEvent {
+    /// Returns the payload as text, `None` for binary payloads which are not valid UTF-8
+    pub fn payload_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.payload).ok()
+    }
+}
+
 #[derive(Debug)]
 pub enum MqttClientEvent {
     Connect,
@@ -79,21 +86,10 @@ pub fn run_mqtt_client(
             match poll_res {
                 Ok(event) => match event {
                     rumqttc::Event::Incoming(rumqttc::Packet::Publish(p)) => {
-                        let payload = match String::from_utf8(p.payload.to_vec()) {
-                            Ok(payload) => payload,
-                            Err(err) => {
-                                log::warn!(
-                                    "Cannot parse mqtt payload for topic [{}] to string. Error: {}",
-                                    p.topic,
-                                    err
-                                );
-                                continue;
-                            }
-                        };
                         let pe = MqttPublishEvent {
                             topic: p.topic,
                             retain: p.retain,
-                            payload,
+                            payload: p.payload.to_vec(),
                             duplicate: p.dup,
                             qos: p.qos,
                         };
@@ -1,13 +1,15 @@
 use crate::{
     cron_manager::CronManager,
+    deferred_trigger_manager::DeferredTriggerManager,
     device_manager::DeviceManager,
     mqtt_client::ManagedMqttClient,
     rules::{
-        add_solar_triggers, queries_init_materialized, schedule_cron, subscribe_mqtt_trigger, unsubscribe_mqtt_trigger,
-        Rule,
+        add_solar_triggers, arm_stale_triggers, queries_init_materialized, schedule_cron, subscribe_mqtt_trigger,
+        unsubscribe_mqtt_trigger, Rule,
     },
     solar_events::SolarEventManager,
     timer_manager::TimerManager,
+    trigger_state::TriggerStateStore,
     virtual_devices::VirtualDevice,
 };
 use color_eyre::eyre::Result;
@@ -68,15 +70,19 @@ impl RuleManager {
         solar_events: &SolarEventManager,
         dm: &DeviceManager,
         vds: &HashMap<DeviceRef, VirtualDevice>,
+        deferred: &DeferredTriggerManager,
     ) -> Result<&mut Rule> {
         let rule = self.entry(hash).or_insert(rule);
         schedule_cron(hash, rule, cron);
         subscribe_mqtt_trigger(rule, mqtt_client).await?;
         add_solar_triggers(hash, rule, solar_events).await;
-        queries_init_materialized(rule, &*dm.read().await, vds);
+        let devices = dm.read().await;
+        queries_init_materialized(rule, &devices, vds);
+        arm_stale_triggers(hash, rule, &devices, deferred);
         Ok(rule)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn remove_rule(
         &mut self,
         hash: ConfigItemHash,
@@ -84,9 +90,13 @@ impl RuleManager {
         mqtt_client: &ManagedMqttClient,
         solar_events: &SolarEventManager,
         timers: &TimerManager,
+        deferred: &DeferredTriggerManager,
+        trigger_states: &TriggerStateStore,
     ) -> Result<Option<Rule>> {
         if let Some(rule) = self.remove(&hash) {
             timers.remove_timers_for_rule(hash);
+            deferred.remove_for_rule(hash);
+            trigger_states.remove_for_rule(hash);
             cron.remove_cron_schedule_for_rule(hash);
             solar_events.remove_triggers_by_rule(hash).await?;
             unsubscribe_mqtt_trigger(&rule, mqtt_client).await?;
@@ -103,20 +113,23 @@ impl RuleManager {
         };
         for (_, rule) in self.iter_mut() {
             for trigger in rule.triggers.iter_mut() {
-                match trigger {
+                trigger.visit_mut(&mut |trigger| match trigger {
                     crate::rules::RuleTrigger::PropertyTriggered { ref mut queries, .. } => {
                         for query in queries.iter_mut() {
                             query.add_materialized(device_ref.homie_domain(), device_ref.device_id(), desc);
                         }
                     }
-                    crate::rules::RuleTrigger::PropertyChanged { ref mut queries, .. } => {
+                    crate::rules::RuleTrigger::PropertyChanged { ref mut queries, .. }
+                    | crate::rules::RuleTrigger::PropertyStale { ref mut queries, .. }
+                    | crate::rules::RuleTrigger::PropertyCrossed { ref mut queries, .. }
+                    | crate::rules::RuleTrigger::PropertyRate { ref mut queries, .. } => {
                         for query in queries.iter_mut() {
                             query.add_materialized(device_ref.homie_domain(), device_ref.device_id(), desc);
                         }
                     }
 
                     _ => {}
-                }
+                });
             }
         }
     }
@@ -126,50 +139,57 @@ impl RuleManager {
         };
         for (_, rule) in self.iter_mut() {
             for trigger in rule.triggers.iter_mut() {
-                match trigger {
+                trigger.visit_mut(&mut |trigger| match trigger {
                     crate::rules::RuleTrigger::PropertyTriggered { ref mut queries, .. } => {
                         for query in queries.iter_mut() {
                             query.remove_materialized(device_ref.homie_domain(), device_ref.device_id(), desc);
                         }
                     }
-                    crate::rules::RuleTrigger::PropertyChanged { ref mut queries, .. } => {
+                    crate::rules::RuleTrigger::PropertyChanged { ref mut queries, .. }
+                    | crate::rules::RuleTrigger::PropertyStale { ref mut queries, .. }
+                    | crate::rules::RuleTrigger::PropertyCrossed { ref mut queries, .. }
+                    | crate::rules::RuleTrigger::PropertyRate { ref mut queries, .. } => {
                         for query in queries.iter_mut() {
                             query.remove_materialized(device_ref.homie_domain(), device_ref.device_id(), desc);
                         }
                     }
 
                     _ => {}
-                }
+                });
             }
         }
     }
     pub fn queries_virtual_device_updated(&mut self, device_ref: &DeviceRef, desc: &HomieDeviceDescription) {
         for (_, rule) in self.iter_mut() {
             for trigger in rule.triggers.iter_mut() {
-                if let crate::rules::RuleTrigger::OnSetEventTrigger {
-                    queries: ref mut on_set_queries,
-                    ..
-                } = trigger
-                {
-                    for query in on_set_queries.iter_mut() {
-                        query.add_materialized(device_ref.homie_domain(), device_ref.device_id(), desc);
+                trigger.visit_mut(&mut |trigger| {
+                    if let crate::rules::RuleTrigger::OnSetEventTrigger {
+                        queries: ref mut on_set_queries,
+                        ..
+                    } = trigger
+                    {
+                        for query in on_set_queries.iter_mut() {
+                            query.add_materialized(device_ref.homie_domain(), device_ref.device_id(), desc);
+                        }
                     }
-                }
+                });
             }
         }
     }
     pub fn queries_virtual_device_removed(&mut self, device_ref: &DeviceRef, desc: &HomieDeviceDescription) {
         for (_, rule) in self.iter_mut() {
             for trigger in rule.triggers.iter_mut() {
-                if let crate::rules::RuleTrigger::OnSetEventTrigger {
-                    queries: ref mut on_set_queries,
-                    ..
-                } = trigger
-                {
-                    for query in on_set_queries.iter_mut() {
-                        query.remove_materialized(device_ref.homie_domain(), device_ref.device_id(), desc);
+                trigger.visit_mut(&mut |trigger| {
+                    if let crate::rules::RuleTrigger::OnSetEventTrigger {
+                        queries: ref mut on_set_queries,
+                        ..
+                    } = trigger
+                    {
+                        for query in on_set_queries.iter_mut() {
+                            query.remove_materialized(device_ref.homie_domain(), device_ref.device_id(), desc);
+                        }
                     }
-                }
+                });
             }
         }
     }
@@ -1,9 +1,8 @@
-use super::RuleContext;
+use super::{json_value_string, RuleContext};
 use crate::lua_runtime::{
     setup_custom_loader, LuaEvent, LuaHomie, LuaTimer, LuaUtils, LuaValueStore, LuaVirtualDecvice,
 };
 use crate::rules::{MapSetFrom, RuleAction, TimerDef};
-use crate::solar_events::SolarEvent;
 use crate::{
     rules::{Rule, RuleTriggerEvent},
     timer_manager::TimerManager,
@@ -63,7 +62,7 @@ pub async fn execute_rule_action<'a>(
         }
         crate::rules::RuleAction::MapSet { target, mapping, timer } => {
             if ignore_timer || timer.is_none() {
-                match trigger_event {
+                match trigger_event.source_event() {
                     RuleTriggerEvent::PropertyChanged { to, .. } => {
                         if let MappingResult::Mapped(value) = mapping.map_to(&MapSetFrom::HomieValue(Cow::Borrowed(to)))
                         {
@@ -90,33 +89,22 @@ pub async fn execute_rule_action<'a>(
                             ctx.dm.set_command(target, value).await?;
                         }
                     }
-                    RuleTriggerEvent::Mqtt(cow) => {
+                    RuleTriggerEvent::Mqtt { event, extracted } => {
+                        let value = match extracted {
+                            Some(extracted) => Cow::Owned(json_value_string(extracted)),
+                            None => Cow::Owned(String::from_utf8_lossy(&event.payload).into_owned()),
+                        };
+                        if let MappingResult::Mapped(value) = mapping.map_to(&MapSetFrom::String(value)) {
+                            ctx.dm.set_command(target, value).await?;
+                        }
+                    }
+                    RuleTriggerEvent::Solar(solar_event) => {
                         if let MappingResult::Mapped(value) =
-                            mapping.map_to(&MapSetFrom::String(Cow::Borrowed(&cow.payload)))
+                            mapping.map_to(&MapSetFrom::SolarPhase(Cow::Borrowed(solar_event.phase())))
                         {
                             ctx.dm.set_command(target, value).await?;
                         }
                     }
-                    RuleTriggerEvent::Solar(cow) => match cow {
-                        Cow::Borrowed(SolarEvent::At(solar_phase))
-                        | Cow::Borrowed(SolarEvent::After(solar_phase, _))
-                        | Cow::Borrowed(SolarEvent::Before(solar_phase, _)) => {
-                            if let MappingResult::Mapped(value) =
-                                mapping.map_to(&MapSetFrom::SolarPhase(Cow::Borrowed(solar_phase)))
-                            {
-                                ctx.dm.set_command(target, value).await?;
-                            }
-                        }
-                        Cow::Owned(SolarEvent::At(solar_phase))
-                        | Cow::Owned(SolarEvent::After(solar_phase, _))
-                        | Cow::Owned(SolarEvent::Before(solar_phase, _)) => {
-                            if let MappingResult::Mapped(value) =
-                                mapping.map_to(&MapSetFrom::SolarPhase(Cow::Borrowed(solar_phase)))
-                            {
-                                ctx.dm.set_command(target, value).await?;
-                            }
-                        }
-                    },
                     _ => return Ok(()),
                 };
             } else if let Some(timer) = timer {
@@ -138,13 +126,21 @@ pub async fn execute_rule_action<'a>(
         RuleAction::Mqtt {
             topic,
             value,
+            binary,
             qos,
             retain,
         } => {
+            let payload = match binary {
+                Some(encoding) => encoding.decode(value)?,
+                None => value.as_bytes().to_vec(),
+            };
             ctx.mqtt_client
-                .publish(topic, HomieMQTTClient::map_qos(qos), *retain, value.as_bytes())
+                .publish(topic, HomieMQTTClient::map_qos(qos), *retain, payload)
                 .await?;
         }
+        RuleAction::Broadcast { subtopic, value } => {
+            ctx.vdm.broadcaster().broadcast(subtopic, value.as_str()).await?;
+        }
         RuleAction::Timer { timer } => {
             handle_timer(rule_hash, rule_name, None, trigger_event, timer, ctx.timers).await?;
         }
@@ -225,7 +221,10 @@ pub(crate) async fn run_script(
     log::trace!("preparing script run");
     let lua = Lua::new();
 
-    let lua_homie = LuaHomie { dm: ctx.dm.clone() };
+    let lua_homie = LuaHomie {
+        dm: ctx.dm.clone(),
+        broadcaster: ctx.vdm.broadcaster(),
+    };
     let lua_virtual_device = LuaVirtualDecvice {
         vdm: ctx.vdm.as_proxy(),
     };
@@ -237,6 +236,7 @@ pub(crate) async fn run_script(
 
     let lua_value_store = LuaValueStore {
         store: ctx.value_store.clone(),
+        events: ctx.value_store_events.clone(),
     };
 
     let lua_event = LuaEvent {
@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+
+use hc_homie5::store::DeviceStore;
+
+use super::{match_trigger_event, while_condition::match_whilecondition_set, RuleContext};
+use crate::{
+    rules::{RuleTrigger, RuleTriggerEvent},
+    trigger_state::{TriggerState, TriggerStateKey},
+};
+
+/// Records the event for the inner triggers of an all_of trigger it matches. Returns the events of all
+/// inner triggers (in the order of the triggers) once every inner trigger matched within the time window.
+/// Unordered triggers keep the latest event per inner trigger and drop events which left the window.
+/// Ordered triggers only accept an event for the next inner trigger and start over with the first one.
+/// The `while` condition is only evaluated for the inner triggers which record the event.
+pub(crate) fn match_all_of(
+    key: TriggerStateKey,
+    triggers: &[RuleTrigger],
+    within: Duration,
+    ordered: bool,
+    event: &RuleTriggerEvent<'_>,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> Option<Vec<RuleTriggerEvent<'static>>> {
+    let matched: Vec<usize> = triggers
+        .iter()
+        .enumerate()
+        .filter(|(_, trigger)| match_trigger_event(trigger, event))
+        .map(|(index, _)| index)
+        .collect();
+    if matched.is_empty() {
+        return None;
+    }
+    let holds = |index: usize| match_whilecondition_set(triggers[index].while_condition(), devices, ctx);
+    ctx.trigger_states.update(key, |state| {
+        let now = Instant::now();
+        let expired = |entry: &Option<(Instant, RuleTriggerEvent<'static>)>| {
+            entry.as_ref().is_some_and(|(at, _)| now.duration_since(*at) > within)
+        };
+        let mut matches = match state.take() {
+            Some(TriggerState::AllOf { matches }) if matches.len() == triggers.len() => matches,
+            _ => vec![None; triggers.len()],
+        };
+
+        if ordered {
+            // the first match is the oldest one, the progress starts over once it left the window
+            if expired(&matches[0]) {
+                matches.fill(None);
+            }
+            let next = matches.iter().position(Option::is_none).unwrap_or_default();
+            if matched.contains(&next) {
+                if holds(next) {
+                    matches[next] = Some((now, event.to_owned()));
+                }
+            } else if matched.contains(&0) && holds(0) {
+                matches.fill(None);
+                matches[0] = Some((now, event.to_owned()));
+            }
+        } else {
+            for entry in matches.iter_mut().filter(|entry| expired(entry)) {
+                *entry = None;
+            }
+            for index in matched.into_iter().filter(|index| holds(*index)) {
+                matches[index] = Some((now, event.to_owned()));
+            }
+        }
+
+        if matches.iter().all(Option::is_some) {
+            *state = None;
+            Some(matches.into_iter().flatten().map(|(_, event)| event).collect())
+        } else {
+            *state = Some(TriggerState::AllOf { matches });
+            None
+        }
+    })
+}
@@ -0,0 +1,49 @@
+use super::{fire_trigger, mqtt_topic_match, while_condition::match_whilecondition_set, RuleContext};
+use crate::rules::RuleTrigger;
+use hc_homie5::store::DeviceStore;
+use homie5::Homie5Message;
+
+pub async fn run_broadcast_rules(message: &Homie5Message, ctx: &RuleContext<'_>) {
+    let Homie5Message::Broadcast { subtopic, data, .. } = message else {
+        return;
+    };
+    let devices = &ctx.dm.read().await;
+    for (hash, rule) in ctx.rules.iter() {
+        let Some(trigger_index) = rule
+            .triggers
+            .iter()
+            .position(|trigger| match_broadcast(subtopic, data, trigger, devices, ctx))
+        else {
+            continue;
+        };
+        if let Ok(event) = message.try_into() {
+            fire_trigger(*hash, rule, trigger_index, event, ctx).await;
+        }
+    }
+}
+
+fn match_broadcast(
+    subtopic: &str,
+    data: &String,
+    trigger: &RuleTrigger,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
+    match trigger {
+        RuleTrigger::BroadcastTrigger {
+            broadcast,
+            trigger_value,
+            r#while,
+            ..
+        } => {
+            if !mqtt_topic_match(broadcast, subtopic) {
+                return false;
+            }
+            if !trigger_value.as_ref().is_none_or(|cond| cond.evaluate(data)) {
+                return false;
+            }
+            match_whilecondition_set(r#while.as_ref(), devices, ctx)
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,80 @@
+use super::{
+    fire_trigger, match_all_of, match_count, match_mqtt_trigger_event, match_prop_change_event, match_prop_set_event,
+    match_prop_trigger_event, match_sequence, while_condition::match_whilecondition_set, RuleContext,
+};
+use crate::{
+    rules::{RuleTrigger, RuleTriggerEvent},
+    trigger_state::TriggerStateKey,
+};
+
+/// Evaluates the composite triggers (triggers combining other triggers, e.g. sequences) of all rules
+/// for an incoming trigger event and runs the rules whose composite trigger fired.
+/// A rule is run at most once per event, with the event of its first fired trigger.
+pub async fn run_composite_rules(event: &RuleTriggerEvent<'_>, ctx: &RuleContext<'_>) {
+    let devices = ctx.dm.read().await;
+    let mut fired = Vec::new();
+    for (hash, rule) in ctx.rules.iter() {
+        let mut rule_event = None;
+        // all triggers need to be evaluated to keep their state up to date
+        for (trigger_index, trigger) in rule.triggers.iter().enumerate() {
+            let key = TriggerStateKey::new(*hash, trigger_index, "");
+            let trigger_event = match trigger {
+                RuleTrigger::SequenceTrigger { sequence, within, .. } => {
+                    match_sequence(key, sequence, *within, event, &devices, ctx).then(|| event.to_owned())
+                }
+                RuleTrigger::AllOfTrigger {
+                    all_of,
+                    within,
+                    ordered,
+                    ..
+                } => match_all_of(key, all_of, *within, *ordered, event, &devices, ctx).map(|events| {
+                    RuleTriggerEvent::AllOf {
+                        event: Box::new(event.to_owned()),
+                        events,
+                    }
+                }),
+                RuleTrigger::CountTrigger {
+                    count,
+                    threshold,
+                    window,
+                    cooldown,
+                    ..
+                } => match_count(key, count, *threshold, *window, *cooldown, event, &devices, ctx).map(|count| {
+                    RuleTriggerEvent::Counted {
+                        event: Box::new(event.to_owned()),
+                        count,
+                        window: *window,
+                    }
+                }),
+                _ => None,
+            };
+            if let Some(trigger_event) = trigger_event {
+                if rule_event.is_none() && match_whilecondition_set(trigger.while_condition(), &devices, ctx) {
+                    rule_event = Some((trigger_index, trigger_event));
+                }
+            }
+        }
+        if let Some((trigger_index, rule_event)) = rule_event {
+            fired.push((*hash, rule, trigger_index, rule_event));
+        }
+    }
+    drop(devices);
+
+    for (hash, rule, trigger_index, rule_event) in fired {
+        fire_trigger(hash, rule, trigger_index, rule_event, ctx).await;
+    }
+}
+
+/// Checks if a trigger nested inside a composite trigger matches the event. The `while` condition of
+/// the nested trigger is not evaluated, it only applies to the inner trigger which records the event.
+pub(crate) fn match_trigger_event(trigger: &RuleTrigger, event: &RuleTriggerEvent<'_>) -> bool {
+    match event {
+        RuleTriggerEvent::PropertyChanged { prop, from, to } => {
+            match_prop_change_event(prop, trigger, from.as_ref().as_ref(), to)
+        }
+        RuleTriggerEvent::PropertyTriggered { prop, value } => match_prop_trigger_event(prop, trigger, value),
+        RuleTriggerEvent::Mqtt { event, .. } => match_mqtt_trigger_event(event, trigger),
+        RuleTriggerEvent::OnSet { prop, value } => match_prop_set_event(prop, value, trigger),
+        _ => false,
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use hc_homie5::store::DeviceStore;
+
+use super::{match_trigger_event, while_condition::match_whilecondition_set, RuleContext};
+use crate::{
+    rules::{RuleTrigger, RuleTriggerEvent},
+    trigger_state::{TriggerState, TriggerStateKey},
+};
+
+/// Counts the matches of the inner trigger of a count trigger within the sliding window.
+/// Returns the number of matches once the threshold is reached. The count starts over after the
+/// trigger fired and no further firing happens until the optional cooldown elapsed.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn match_count(
+    key: TriggerStateKey,
+    trigger: &RuleTrigger,
+    threshold: usize,
+    window: Duration,
+    cooldown: Option<Duration>,
+    event: &RuleTriggerEvent<'_>,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> Option<usize> {
+    if !(match_trigger_event(trigger, event) && match_whilecondition_set(trigger.while_condition(), devices, ctx)) {
+        return None;
+    }
+    ctx.trigger_states.update(key, |state| {
+        let now = Instant::now();
+        let (mut matches, cooldown_until) = match state.take() {
+            Some(TriggerState::Count {
+                matches,
+                cooldown_until,
+            }) => (matches, cooldown_until),
+            _ => (VecDeque::new(), None),
+        };
+        matches.push_back(now);
+        while matches.front().is_some_and(|first| now.duration_since(*first) > window) {
+            matches.pop_front();
+        }
+
+        let cooling_down = cooldown_until.is_some_and(|until| now < until);
+        if !cooling_down && matches.len() >= threshold {
+            let count = matches.len();
+            *state = Some(TriggerState::Count {
+                matches: VecDeque::new(),
+                cooldown_until: cooldown.map(|cooldown| now + cooldown),
+            });
+            Some(count)
+        } else {
+            *state = Some(TriggerState::Count {
+                matches,
+                cooldown_until,
+            });
+            None
+        }
+    })
+}
@@ -0,0 +1,100 @@
+use homie5::{HomieValue, PropertyRef, ToTopic};
+
+use super::{fire_trigger, match_prop_subject, while_condition::match_whilecondition_set, RuleContext};
+use crate::{
+    rules::{CrossingTrigger, RuleTrigger, RuleTriggerEvent},
+    trigger_state::{TriggerState, TriggerStateKey, TriggerStateStore},
+};
+
+/// Runs the rules with a crossing trigger for the property whose value crossed one of the thresholds
+pub async fn run_crossing_rules(
+    prop: &PropertyRef,
+    value: &HomieValue,
+    trigger_event: &RuleTriggerEvent<'_>,
+    ctx: &RuleContext<'_>,
+) {
+    let Some(value) = numeric_value(value) else {
+        return;
+    };
+    let devices = ctx.dm.read().await;
+    let mut fired = Vec::new();
+    for (hash, rule) in ctx.rules.iter() {
+        let mut fired_index = None;
+        // all triggers need to be evaluated to keep their armed state up to date
+        for (trigger_index, trigger) in rule.triggers.iter().enumerate() {
+            let RuleTrigger::PropertyCrossed {
+                properties,
+                queries,
+                crossing,
+                r#while,
+                ..
+            } = trigger
+            else {
+                continue;
+            };
+            if !match_prop_subject(prop, properties, queries) {
+                continue;
+            }
+            let key = TriggerStateKey::new(*hash, trigger_index, prop.to_topic().build());
+            if match_crossing(key, crossing, value, ctx.trigger_states)
+                && match_whilecondition_set(r#while.as_ref(), &devices, ctx)
+            {
+                fired_index.get_or_insert(trigger_index);
+            }
+        }
+        if let Some(trigger_index) = fired_index {
+            fired.push((*hash, rule, trigger_index));
+        }
+    }
+    drop(devices);
+
+    for (hash, rule, trigger_index) in fired {
+        fire_trigger(hash, rule, trigger_index, trigger_event.to_owned(), ctx).await;
+    }
+}
+
+/// Returns the value of numeric homie values as float
+pub(crate) fn numeric_value(value: &HomieValue) -> Option<f64> {
+    match value {
+        HomieValue::Integer(value) => Some(*value as f64),
+        HomieValue::Float(value) => Some(*value),
+        _ => None,
+    }
+}
+
+/// Updates the armed state of the thresholds with the new value and returns true if an armed
+/// threshold was crossed. The first value only initializes the state.
+fn match_crossing(key: TriggerStateKey, crossing: &CrossingTrigger, value: f64, states: &TriggerStateStore) -> bool {
+    states.update(key, |state| {
+        let Some(TriggerState::Crossing {
+            above_armed,
+            below_armed,
+        }) = state
+        else {
+            *state = Some(TriggerState::Crossing {
+                above_armed: crossing.above.is_none_or(|above| value <= above),
+                below_armed: crossing.below.is_none_or(|below| value >= below),
+            });
+            return false;
+        };
+
+        let mut crossed = false;
+        if let Some(above) = crossing.above {
+            if *above_armed && value > above {
+                *above_armed = false;
+                crossed = true;
+            } else if !*above_armed && value <= above - crossing.hysteresis {
+                *above_armed = true;
+            }
+        }
+        if let Some(below) = crossing.below {
+            if *below_armed && value < below {
+                *below_armed = false;
+                crossed = true;
+            } else if !*below_armed && value >= below + crossing.hysteresis {
+                *below_armed = true;
+            }
+        }
+        crossed
+    })
+}
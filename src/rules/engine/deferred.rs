@@ -0,0 +1,22 @@
+use crate::deferred_trigger_manager::DeferredTriggerEvent;
+
+use super::{fire_deferred_trigger, while_condition::match_whilecondition_set, RuleContext};
+
+pub async fn run_deferred_trigger_rules(event: &DeferredTriggerEvent, ctx: &RuleContext<'_>) {
+    // ignore events of triggers which were cancelled or replaced in the meantime
+    if !ctx.deferred.complete(event) {
+        return;
+    }
+    let Some(rule) = ctx.rules.get(&event.rule_hash) else {
+        return;
+    };
+    let Some(trigger) = rule.triggers.get(event.trigger_index) else {
+        return;
+    };
+    let devices = ctx.dm.read().await;
+    if !match_whilecondition_set(trigger.while_condition(), &devices, ctx) {
+        return;
+    }
+    drop(devices);
+    fire_deferred_trigger(event, rule, ctx).await;
+}
@@ -0,0 +1,208 @@
+use std::borrow::Cow;
+
+use hc_homie5::model::DiscoveryAction;
+use hc_homie5::store::DeviceStore;
+use homie5::{device_description::HomieDeviceDescription, DeviceRef, HomieDeviceStatus, HomieID};
+
+use super::{cancel_device_holds, fire_trigger, while_condition::match_whilecondition_set, RuleContext};
+use crate::rules::{DeviceAlertEvent, DeviceLifecycleEvent, DeviceQueryDefinition, RuleTrigger, RuleTriggerEvent};
+
+pub async fn run_device_rules(event: &DiscoveryAction, ctx: &RuleContext<'_>) {
+    match event {
+        DiscoveryAction::StateChanged { device, from, to } => {
+            let devices = ctx.dm.read().await;
+            let desc = devices.get_device(device).and_then(|d| d.description.as_ref());
+            if matches!(to, HomieDeviceStatus::Lost | HomieDeviceStatus::Disconnected) {
+                cancel_device_holds(device, desc, ctx);
+            }
+            for (hash, rule) in ctx.rules.iter() {
+                let Some(trigger_index) = rule
+                    .triggers
+                    .iter()
+                    .position(|trigger| match_device_state_change(device, desc, trigger, from, to, &devices, ctx))
+                else {
+                    continue;
+                };
+                if let Ok(event) = event.try_into() {
+                    fire_trigger(*hash, rule, trigger_index, event, ctx).await;
+                }
+            }
+        }
+        DiscoveryAction::DeviceRemoved(device) => {
+            cancel_device_holds(&device.ident, device.description.as_ref(), ctx);
+            let devices = ctx.dm.read().await;
+            for (hash, rule) in ctx.rules.iter() {
+                let Some(trigger_index) = rule.triggers.iter().position(|trigger| {
+                    match_device_lifecycle(
+                        &device.ident,
+                        device.description.as_ref(),
+                        trigger,
+                        DeviceLifecycleEvent::Removed,
+                        &devices,
+                        ctx,
+                    )
+                }) else {
+                    continue;
+                };
+                if let Ok(event) = event.try_into() {
+                    fire_trigger(*hash, rule, trigger_index, event, ctx).await;
+                }
+            }
+        }
+        DiscoveryAction::DeviceAlert { device, alert_id, .. }
+        | DiscoveryAction::DeviceAlertChanged { device, alert_id, .. }
+        | DiscoveryAction::DeviceAlertCleared { device, alert_id } => {
+            let Ok(trigger_event) = RuleTriggerEvent::try_from(event) else {
+                return;
+            };
+            let message = match event {
+                DiscoveryAction::DeviceAlert { alert, .. }
+                | DiscoveryAction::DeviceAlertChanged { to_alert: alert, .. } => Some(alert),
+                _ => None,
+            };
+            let devices = ctx.dm.read().await;
+            let desc = devices.get_device(device).and_then(|d| d.description.as_ref());
+            for (hash, rule) in ctx.rules.iter() {
+                let Some(trigger_index) = rule
+                    .triggers
+                    .iter()
+                    .position(|trigger| match_device_alert(device, desc, trigger, alert_id, message, &devices, ctx))
+                else {
+                    continue;
+                };
+                fire_trigger(*hash, rule, trigger_index, trigger_event.clone(), ctx).await;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Runs the rules for a newly discovered device. This is called once the device description is known.
+pub async fn run_device_added_rules(device: &DeviceRef, ctx: &RuleContext<'_>) {
+    let devices = ctx.dm.read().await;
+    let desc = devices.get_device(device).and_then(|d| d.description.as_ref());
+    for (hash, rule) in ctx.rules.iter() {
+        let Some(trigger_index) = rule.triggers.iter().position(|trigger| {
+            match_device_lifecycle(device, desc, trigger, DeviceLifecycleEvent::Added, &devices, ctx)
+        }) else {
+            continue;
+        };
+        let event = RuleTriggerEvent::DeviceAdded {
+            device: Cow::Borrowed(device),
+            description: Cow::Owned(desc.cloned()),
+        };
+        fire_trigger(*hash, rule, trigger_index, event, ctx).await;
+    }
+}
+
+/// Checks if the device is part of the configured devices or matched by one of the device queries
+pub(crate) fn match_device_subject(
+    device: &DeviceRef,
+    desc: Option<&HomieDeviceDescription>,
+    devices: &[DeviceRef],
+    queries: &[DeviceQueryDefinition],
+) -> bool {
+    devices.iter().any(|d| d == device) || queries.iter().any(|query| query.match_query(device, desc))
+}
+
+fn match_device_lifecycle(
+    device: &DeviceRef,
+    desc: Option<&HomieDeviceDescription>,
+    trigger: &RuleTrigger,
+    lifecycle_event: DeviceLifecycleEvent,
+    store: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
+    match trigger {
+        RuleTrigger::DeviceLifecycle {
+            devices,
+            queries,
+            device_event,
+            r#while,
+            ..
+        } => {
+            *device_event == lifecycle_event
+                && match_device_subject(device, desc, devices, queries)
+                && match_whilecondition_set(r#while.as_ref(), store, ctx)
+        }
+        _ => false,
+    }
+}
+
+/// Matches a raised (with `message`) or cleared (without `message`) device alert
+fn match_device_alert(
+    device: &DeviceRef,
+    desc: Option<&HomieDeviceDescription>,
+    trigger: &RuleTrigger,
+    alert_id: &HomieID,
+    message: Option<&String>,
+    store: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
+    match trigger {
+        RuleTrigger::DeviceAlert {
+            devices,
+            queries,
+            alert,
+            r#while,
+            ..
+        } => {
+            let alert_event = if message.is_some() {
+                DeviceAlertEvent::Raised
+            } else {
+                DeviceAlertEvent::Cleared
+            };
+            if alert.event.is_some_and(|event| event != alert_event) {
+                return false;
+            }
+            if !match_device_subject(device, desc, devices, queries) {
+                return false;
+            }
+            if !alert
+                .id
+                .as_ref()
+                .is_none_or(|cond| cond.evaluate(&alert_id.to_string()))
+            {
+                return false;
+            }
+            if let (Some(cond), Some(message)) = (&alert.message, message) {
+                if !cond.evaluate(message) {
+                    return false;
+                }
+            }
+            match_whilecondition_set(r#while.as_ref(), store, ctx)
+        }
+        _ => false,
+    }
+}
+
+fn match_device_state_change(
+    device: &DeviceRef,
+    desc: Option<&HomieDeviceDescription>,
+    trigger: &RuleTrigger,
+    from: &HomieDeviceStatus,
+    to: &HomieDeviceStatus,
+    store: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
+    match trigger {
+        RuleTrigger::DeviceStateChanged {
+            devices,
+            queries,
+            device_state,
+            r#while,
+            ..
+        } => {
+            if !match_device_subject(device, desc, devices, queries) {
+                return false;
+            }
+            if !device_state.from.as_ref().is_none_or(|cond| cond.evaluate(from))
+                || !device_state.to.as_ref().is_none_or(|cond| cond.evaluate(to))
+            {
+                return false;
+            }
+            match_whilecondition_set(r#while.as_ref(), store, ctx)
+        }
+        _ => false,
+    }
+}
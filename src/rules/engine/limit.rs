@@ -0,0 +1,148 @@
+use std::time::Instant;
+
+use config_watcher::ConfigItemHash;
+use homie5::ToTopic;
+
+use super::{run_rule_actions, RuleContext};
+use crate::{
+    deferred_trigger_manager::DeferredTriggerEvent,
+    rules::{Rule, RuleTriggerEvent},
+    trigger_state::{TriggerState, TriggerStateKey},
+};
+
+/// Builds the id used to delay the firing of a debounced trigger for a single subject
+fn debounce_id(rule_hash: ConfigItemHash, trigger_index: usize, subject: &str) -> String {
+    format!("debounce-{}-{}-{}", rule_hash, trigger_index, subject)
+}
+
+/// Builds the id used to delay the trailing firing of a throttled trigger for a single subject
+fn throttle_id(rule_hash: ConfigItemHash, trigger_index: usize, subject: &str) -> String {
+    format!("throttle-{}-{}-{}", rule_hash, trigger_index, subject)
+}
+
+/// Returns the subject (property, topic, device, ...) the event is about. Debounce and throttle are
+/// applied per subject, this way e.g. a trigger on several properties limits every property on its own.
+fn event_subject(event: &RuleTriggerEvent<'_>) -> String {
+    if let Some(prop) = event.property_ref() {
+        prop.to_topic().build()
+    } else if let Some(topic) = event.mqtt_topic() {
+        topic.to_string()
+    } else if let Some(device) = event.device_ref() {
+        device.to_topic().build()
+    } else if let Some(subtopic) = event.broadcast_subtopic() {
+        subtopic.to_string()
+    } else if let Some(request) = event.webhook_request() {
+        request.path.clone()
+    } else if let Some(event) = event.value_store_event() {
+        event.key.clone()
+    } else if let Some(timer_id) = event.timer_id() {
+        timer_id.to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Runs the actions of the rule for its fired trigger, taking the `debounce` and `throttle` options of
+/// the trigger into account. A debounced trigger fires once no further event arrived for the debounce
+/// period, with the last event. A throttled trigger fires at most once per interval.
+pub(crate) async fn fire_trigger<'a>(
+    rule_hash: ConfigItemHash,
+    rule: &Rule,
+    trigger_index: usize,
+    trigger_event: RuleTriggerEvent<'a>,
+    ctx: &RuleContext<'a>,
+) {
+    let Some(trigger) = rule.triggers.get(trigger_index) else {
+        return;
+    };
+    if let Some(debounce) = trigger.debounce() {
+        let subject = event_subject(&trigger_event);
+        ctx.deferred.schedule(
+            rule_hash,
+            trigger_index,
+            debounce_id(rule_hash, trigger_index, &subject),
+            debounce,
+            &trigger_event,
+        );
+        return;
+    }
+    throttle_trigger(rule_hash, rule, trigger_index, trigger_event, ctx).await;
+}
+
+/// Runs the actions of the rule for a deferred trigger event. Events of elapsed debounce periods are
+/// passed on to the throttle, trailing throttle events start a new interval and all other deferred
+/// triggers (e.g. hold periods) are fired like any other trigger.
+pub(crate) async fn fire_deferred_trigger(event: &DeferredTriggerEvent, rule: &Rule, ctx: &RuleContext<'_>) {
+    let trigger_event = (*event.trigger_event).clone();
+    let subject = event_subject(&trigger_event);
+    if event.id == debounce_id(event.rule_hash, event.trigger_index, &subject) {
+        throttle_trigger(event.rule_hash, rule, event.trigger_index, trigger_event, ctx).await;
+    } else if event.id == throttle_id(event.rule_hash, event.trigger_index, &subject) {
+        if let Some(throttle) = rule.triggers.get(event.trigger_index).and_then(|t| t.throttle()) {
+            ctx.trigger_states
+                .update(throttle_key(event.rule_hash, event.trigger_index, &subject), |state| {
+                    *state = Some(TriggerState::Throttle {
+                        until: Instant::now() + throttle.interval,
+                    });
+                });
+        }
+        run_rule_actions(event.rule_hash, rule, trigger_event, ctx).await;
+    } else {
+        fire_trigger(event.rule_hash, rule, event.trigger_index, trigger_event, ctx).await;
+    }
+}
+
+fn throttle_key(rule_hash: ConfigItemHash, trigger_index: usize, subject: &str) -> TriggerStateKey {
+    // prefixed to keep it apart from the state of stateful triggers for the same subject
+    TriggerStateKey::new(rule_hash, trigger_index, format!("throttle:{}", subject))
+}
+
+async fn throttle_trigger<'a>(
+    rule_hash: ConfigItemHash,
+    rule: &Rule,
+    trigger_index: usize,
+    trigger_event: RuleTriggerEvent<'a>,
+    ctx: &RuleContext<'a>,
+) {
+    let Some(throttle) = rule.triggers.get(trigger_index).and_then(|t| t.throttle()) else {
+        run_rule_actions(rule_hash, rule, trigger_event, ctx).await;
+        return;
+    };
+    let subject = event_subject(&trigger_event);
+    let now = Instant::now();
+    ctx.trigger_states.remove_expired_throttles(now);
+    // the end of the running interval, `None` if this event starts a new one
+    let running = ctx
+        .trigger_states
+        .update(throttle_key(rule_hash, trigger_index, &subject), |state| match state {
+            Some(TriggerState::Throttle { until }) if now < *until => Some(*until),
+            _ => {
+                *state = Some(TriggerState::Throttle {
+                    until: now + throttle.interval,
+                });
+                None
+            }
+        });
+
+    match running {
+        None if throttle.leading => run_rule_actions(rule_hash, rule, trigger_event, ctx).await,
+        None => ctx.deferred.schedule(
+            rule_hash,
+            trigger_index,
+            throttle_id(rule_hash, trigger_index, &subject),
+            throttle.interval,
+            &trigger_event,
+        ),
+        // the last event within the interval wins, scheduling it again replaces the pending one
+        Some(until) if throttle.trailing => ctx.deferred.schedule(
+            rule_hash,
+            trigger_index,
+            throttle_id(rule_hash, trigger_index, &subject),
+            until - now,
+            &trigger_event,
+        ),
+        Some(_) => {
+            log::debug!("{} -- trigger {} throttled for {}", rule.name, trigger_index, subject);
+        }
+    }
+}
@@ -1,36 +1,71 @@
 // modules
 mod action;
+mod all_of;
+mod broadcast;
+mod composite;
+mod count;
 mod cron;
+mod crossing;
+mod deferred;
+mod devices;
+mod limit;
 mod mqtt;
 mod properties;
 mod queries;
+mod rate;
+mod sequence;
 mod solar;
+mod stale;
+mod system;
 mod timer;
+mod value_store;
 mod virtual_devices;
+mod webhook;
 mod while_condition;
 
 // re-exports
 pub use action::*;
+pub(crate) use all_of::*;
+pub use broadcast::*;
+pub use composite::*;
+pub(crate) use count::*;
 pub use cron::*;
+pub use crossing::*;
+pub use deferred::*;
+pub use devices::*;
+pub(crate) use limit::*;
 pub use mqtt::*;
 pub use properties::*;
 pub use queries::*;
+pub use rate::*;
+pub(crate) use sequence::*;
 use simple_kv_store::KeyValueStore;
 pub use solar::*;
+pub use stale::*;
+pub use system::*;
 pub use timer::*;
+pub use value_store::*;
 pub use virtual_devices::*;
+pub use webhook::*;
 
 use crate::{
-    device_manager::DeviceManager, lua_runtime::LuaModuleManager, mqtt_client::ManagedMqttClient,
-    rule_manager::RuleManager, timer_manager::TimerManager, virtual_devices::VirtualDeviceManager,
+    calendar::CalendarManager, deferred_trigger_manager::DeferredTriggerManager, device_manager::DeviceManager,
+    lua_runtime::LuaModuleManager, mqtt_client::ManagedMqttClient, rule_manager::RuleManager,
+    solar_events::SolarEventManager, timer_manager::TimerManager, trigger_state::TriggerStateStore,
+    value_store_events::ValueStoreNotifier, virtual_devices::VirtualDeviceManager,
 };
 
 pub struct RuleContext<'a> {
     pub rules: &'a RuleManager,
     pub timers: &'a TimerManager,
+    pub deferred: &'a DeferredTriggerManager,
+    pub trigger_states: &'a TriggerStateStore,
     pub dm: &'a DeviceManager,
     pub vdm: &'a VirtualDeviceManager,
     pub mqtt_client: &'a ManagedMqttClient,
     pub value_store: &'a KeyValueStore,
+    pub value_store_events: &'a ValueStoreNotifier,
     pub lmm: &'a LuaModuleManager,
+    pub calendars: &'a CalendarManager,
+    pub solar_events: &'a SolarEventManager,
 }
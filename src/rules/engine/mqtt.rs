@@ -1,38 +1,102 @@
-use super::{run_rule_actions, while_condition::match_whilecondition_set, RuleContext};
+use super::{fire_trigger, run_composite_rules, while_condition::match_whilecondition_set, RuleContext};
 use crate::{
     mqtt_client::{ManagedMqttClient, MqttPublishEvent},
-    rules::{Rule, RuleTrigger},
+    rules::{Rule, RuleTrigger, RuleTriggerEvent},
 };
 use color_eyre::eyre::Result;
 use hc_homie5::client::HomieMQTTClient;
 use hc_homie5::store::DeviceStore;
+use homie5::client::QoS;
+use std::borrow::Cow;
 
 pub async fn run_mqtt_rules(event: &MqttPublishEvent, ctx: &RuleContext<'_>) {
-    match ctx.vdm.update_member_value_mqtt(&event.topic, &event.payload).await {
-        Ok(_) => {}
-        Err(err) => {
-            log::warn!("Error updating virtual devices with value for {} - {}: {}", event.topic, event.payload, err);
+    if let Some(payload) = event.payload_str() {
+        match ctx.vdm.update_member_value_mqtt(&event.topic, payload).await {
+            Ok(_) => {}
+            Err(err) => {
+                log::warn!("Error updating virtual devices with value for {} - {}: {}", event.topic, payload, err);
+            }
         }
     }
+    run_composite_rules(&event.into(), ctx).await;
     let devices = ctx.dm.read().await;
-    for (hash, rule) in ctx.rules.iter().filter(|(_, rule)| {
-        rule.triggers
+    for (hash, rule) in ctx.rules.iter() {
+        let Some(trigger_index) = rule
+            .triggers
             .iter()
-            .any(|trigger| match_mqtt_trigger(event, trigger, &devices))
-    }) {
-        run_rule_actions(*hash, rule, event.into(), ctx).await;
+            .position(|trigger| match_mqtt_trigger(event, trigger, &devices, ctx))
+        else {
+            continue;
+        };
+        let trigger_event = RuleTriggerEvent::Mqtt {
+            event: Cow::Borrowed(event),
+            extracted: extract_mqtt_value(event, &rule.triggers[trigger_index]).map(Cow::Owned),
+        };
+        fire_trigger(*hash, rule, trigger_index, trigger_event, ctx).await;
     }
 }
 
-pub fn match_mqtt_trigger(event: &MqttPublishEvent, trigger: &RuleTrigger, devices: &DeviceStore) -> bool {
+/// Returns the value extracted from the JSON payload if the mqtt trigger has a `json_path`
+fn extract_mqtt_value(event: &MqttPublishEvent, trigger: &RuleTrigger) -> Option<serde_json::Value> {
+    let RuleTrigger::MqttTrigger {
+        json_path: Some(json_path),
+        ..
+    } = trigger
+    else {
+        return None;
+    };
+    extract_json_value(&event.payload, json_path)
+}
+
+fn extract_json_value(payload: &[u8], json_path: &str) -> Option<serde_json::Value> {
+    let payload: serde_json::Value = serde_json::from_slice(payload).ok()?;
+    payload.pointer(&json_pointer(json_path)).cloned()
+}
+
+/// Converts a dotted path (e.g. `state.values[0]` or `$.state.values.0`) to a JSON pointer,
+/// JSON pointers (e.g. `/state/values/0`) are returned as they are
+fn json_pointer(path: &str) -> String {
+    if path.starts_with('/') {
+        return path.to_string();
+    }
+    path.trim_start_matches('$')
+        .replace('[', ".")
+        .replace(']', "")
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+/// Returns the string representation of a JSON value, strings without quotes
+pub(crate) fn json_value_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+pub fn match_mqtt_trigger(
+    event: &MqttPublishEvent,
+    trigger: &RuleTrigger,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
+    match_mqtt_trigger_event(event, trigger) && match_whilecondition_set(trigger.while_condition(), devices, ctx)
+}
+
+/// Checks if the mqtt message matches the mqtt trigger, without evaluating its `while` condition
+pub(crate) fn match_mqtt_trigger_event(event: &MqttPublishEvent, trigger: &RuleTrigger) -> bool {
     if let RuleTrigger::MqttTrigger {
         topic,
         skip_retained,
         skip_duplicated,
         check_qos,
         qos,
+        json_path,
+        binary,
         trigger_value,
-        r#while,
+        ..
     } = trigger
     {
         if mqtt_topic_match(topic, &event.topic) {
@@ -48,33 +112,47 @@ pub fn match_mqtt_trigger(event: &MqttPublishEvent, trigger: &RuleTrigger, devic
                 return false;
             }
 
-            if !trigger_value.evaluate(&event.payload) {
-                return false;
-            }
-
-            return match_whilecondition_set(r#while.as_ref(), devices);
+            return match (json_path, binary) {
+                // a payload which is no JSON or does not contain the field does not match
+                (Some(json_path), _) => extract_json_value(&event.payload, json_path)
+                    .is_some_and(|extracted| trigger_value.evaluate(&json_value_string(&extracted))),
+                (None, Some(encoding)) => trigger_value.evaluate(&encoding.encode(&event.payload)),
+                // binary payloads only match triggers which opted into binary matching
+                (None, None) => event
+                    .payload_str()
+                    .is_some_and(|payload| trigger_value.evaluate(&payload.to_string())),
+            };
         }
     }
     false
 }
 
 pub async fn subscribe_mqtt_trigger(rule: &Rule, mqtt_client: &ManagedMqttClient) -> Result<()> {
-    for trigger in rule.triggers.iter() {
-        if let RuleTrigger::MqttTrigger { topic, qos, .. } = trigger {
-            mqtt_client.subscribe(topic, HomieMQTTClient::map_qos(qos)).await?
-        }
+    for (topic, qos) in mqtt_trigger_topics(rule) {
+        mqtt_client.subscribe(topic, HomieMQTTClient::map_qos(qos)).await?
     }
     Ok(())
 }
 
 pub async fn unsubscribe_mqtt_trigger(rule: &Rule, mqtt_client: &ManagedMqttClient) -> Result<()> {
-    for trigger in rule.triggers.iter() {
-        if let RuleTrigger::MqttTrigger { topic, .. } = trigger {
-            mqtt_client.unsubscribe(topic).await?
-        }
+    for (topic, _) in mqtt_trigger_topics(rule) {
+        mqtt_client.unsubscribe(topic).await?
     }
     Ok(())
 }
+
+/// Collects the topics of all mqtt triggers of the rule, including the ones nested in composite triggers
+fn mqtt_trigger_topics(rule: &Rule) -> Vec<(&str, &QoS)> {
+    let mut topics = Vec::new();
+    for trigger in rule.triggers.iter() {
+        trigger.visit(&mut |trigger| {
+            if let RuleTrigger::MqttTrigger { topic, qos, .. } = trigger {
+                topics.push((topic.as_str(), qos));
+            }
+        });
+    }
+    topics
+}
 pub fn mqtt_topic_match(filter: &str, topic: &str) -> bool {
     // Quick return for exact matches
     if filter == topic {
@@ -1,9 +1,14 @@
-use crate::rules::RuleTrigger;
+use crate::rules::{Rule, RuleTrigger, RuleTriggerEvent};
+use config_watcher::ConfigItemHash;
 use hc_homie5::model::DiscoveryAction;
+use hc_homie5::query::MaterializedQuery;
 use hc_homie5::store::DeviceStore;
-use homie5::{HomieValue, PropertyRef, ToTopic};
+use homie5::{device_description::HomieDeviceDescription, DeviceRef, HomieValue, PropertyRef, ToTopic};
 
-use super::{run_rule_actions, while_condition::match_whilecondition_set, RuleContext};
+use super::{
+    fire_trigger, rearm_stale_triggers, run_composite_rules, run_crossing_rules, run_rate_rules,
+    while_condition::match_whilecondition_set, RuleContext,
+};
 
 pub async fn run_subject_rules(event: &DiscoveryAction, ctx: &RuleContext<'_>) {
     match event {
@@ -19,17 +24,34 @@ pub async fn run_subject_rules(event: &DiscoveryAction, ctx: &RuleContext<'_>) {
                     );
                 }
             }
+            for (hash, rule) in ctx.rules.iter() {
+                rearm_stale_triggers(*hash, rule, prop, to, ctx.deferred);
+            }
+            // the initial value is needed to initialize the crossing state and value history
+            if let Ok(trigger_event) = event.try_into() {
+                run_crossing_rules(prop, to, &trigger_event, ctx).await;
+                run_rate_rules(prop, to, &trigger_event, ctx).await;
+            }
             if from.is_none() {
                 return;
             }
+            if let Ok(trigger_event) = event.try_into() {
+                for (hash, rule) in ctx.rules.iter() {
+                    update_prop_change_holds(*hash, rule, prop, from.as_ref(), to, &trigger_event, ctx);
+                }
+                run_composite_rules(&trigger_event, ctx).await;
+            }
             let devices = ctx.dm.read().await;
-            for (hash, rule) in ctx.rules.iter().filter(|(_, rule)| {
-                rule.triggers
+            for (hash, rule) in ctx.rules.iter() {
+                let Some(trigger_index) = rule
+                    .triggers
                     .iter()
-                    .any(|trigger| match_prop_change(prop, trigger, from.as_ref(), to, &devices))
-            }) {
+                    .position(|trigger| match_prop_change(prop, trigger, from.as_ref(), to, &devices, ctx))
+                else {
+                    continue;
+                };
                 if let Ok(event) = event.try_into() {
-                    run_rule_actions(*hash, rule, event, ctx).await;
+                    fire_trigger(*hash, rule, trigger_index, event, ctx).await;
                 }
             }
         }
@@ -45,14 +67,28 @@ pub async fn run_subject_rules(event: &DiscoveryAction, ctx: &RuleContext<'_>) {
                     );
                 }
             }
+            for (hash, rule) in ctx.rules.iter() {
+                rearm_stale_triggers(*hash, rule, prop, value, ctx.deferred);
+            }
+            if let Ok(trigger_event) = event.try_into() {
+                run_crossing_rules(prop, value, &trigger_event, ctx).await;
+                run_rate_rules(prop, value, &trigger_event, ctx).await;
+                for (hash, rule) in ctx.rules.iter() {
+                    update_prop_trigger_holds(*hash, rule, prop, value, &trigger_event, ctx);
+                }
+                run_composite_rules(&trigger_event, ctx).await;
+            }
             let devices = ctx.dm.read().await;
-            for (hash, rule) in ctx.rules.iter().filter(|(_, rule)| {
-                rule.triggers
+            for (hash, rule) in ctx.rules.iter() {
+                let Some(trigger_index) = rule
+                    .triggers
                     .iter()
-                    .any(|trigger| match_prop_trigger(prop, trigger, value, &devices))
-            }) {
+                    .position(|trigger| match_prop_trigger(prop, trigger, value, &devices, ctx))
+                else {
+                    continue;
+                };
                 if let Ok(event) = event.try_into() {
-                    run_rule_actions(*hash, rule, event, ctx).await;
+                    fire_trigger(*hash, rule, trigger_index, event, ctx).await;
                 }
             }
         }
@@ -60,61 +96,89 @@ pub async fn run_subject_rules(event: &DiscoveryAction, ctx: &RuleContext<'_>) {
     };
 }
 
-fn match_prop_trigger(prop: &PropertyRef, trigger: &RuleTrigger, value: &HomieValue, devices: &DeviceStore) -> bool {
+/// Checks if the property is part of the configured properties or matched by one of the queries
+pub(crate) fn match_prop_subject(
+    prop: &PropertyRef,
+    properties: &[PropertyRef],
+    queries: &[MaterializedQuery],
+) -> bool {
+    properties.iter().any(|p| p == prop) || queries.iter().any(|query| query.match_query(prop))
+}
+
+/// Builds the id used to track the hold period of a single property for a rule trigger
+pub(crate) fn hold_id(rule_hash: ConfigItemHash, trigger_index: usize, prop: &PropertyRef) -> String {
+    format!("{}-{}-{}", rule_hash, trigger_index, prop.to_topic().build())
+}
+
+pub(crate) fn match_prop_trigger(
+    prop: &PropertyRef,
+    trigger: &RuleTrigger,
+    value: &HomieValue,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
+    match_prop_trigger_event(prop, trigger, value) && match_whilecondition_set(trigger.while_condition(), devices, ctx)
+}
+
+/// Checks if the value matches the property triggered trigger, without evaluating its `while` condition
+pub(crate) fn match_prop_trigger_event(prop: &PropertyRef, trigger: &RuleTrigger, value: &HomieValue) -> bool {
     match trigger {
         RuleTrigger::PropertyTriggered {
             properties,
             queries,
             trigger_value,
-            r#while,
+            r#for,
+            ..
         } => {
-            // Check if either properties or queries are non-empty
-            let properties_match = !properties.is_empty() && properties.iter().any(|p| p == prop);
-            let queries_match = !queries.is_empty() && queries.iter().any(|query| query.match_query(prop));
-
-            // If both are empty, return false
-            if properties.is_empty() && queries.is_empty() {
+            // Triggers with a hold period are fired once the period has elapsed
+            if r#for.is_some() {
                 return false;
             }
 
             // If neither properties nor queries match, return false
-            if !(properties_match || queries_match) {
+            if !match_prop_subject(prop, properties, queries) {
                 return false;
             }
 
-            if !trigger_value.evaluate(value) {
-                return false;
-            }
-            match_whilecondition_set(r#while.as_ref(), devices)
+            trigger_value.evaluate(value)
         }
         _ => false,
     }
 }
-fn match_prop_change(
+pub(crate) fn match_prop_change(
     prop: &PropertyRef,
     trigger: &RuleTrigger,
     from: Option<&HomieValue>,
     to: &HomieValue,
     devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
+    match_prop_change_event(prop, trigger, from, to)
+        && match_whilecondition_set(trigger.while_condition(), devices, ctx)
+}
+
+/// Checks if the value change matches the property changed trigger, without evaluating its `while` condition
+pub(crate) fn match_prop_change_event(
+    prop: &PropertyRef,
+    trigger: &RuleTrigger,
+    from: Option<&HomieValue>,
+    to: &HomieValue,
 ) -> bool {
     match trigger {
         RuleTrigger::PropertyChanged {
             properties,
             queries,
             changed,
-            r#while,
+            r#for,
+            ..
         } => {
-            // Check if either properties or queries are non-empty
-            let properties_match = !properties.is_empty() && properties.iter().any(|p| p == prop);
-            let queries_match = !queries.is_empty() && queries.iter().any(|query| query.match_query(prop));
-
-            // If both are empty, return false
-            if properties.is_empty() && queries.is_empty() {
+            // Triggers with a hold period are fired once the period has elapsed
+            if r#for.is_some() {
                 return false;
             }
 
             // If neither properties nor queries match, return false
-            if !(properties_match || queries_match) {
+            if !match_prop_subject(prop, properties, queries) {
                 return false;
             }
 
@@ -127,11 +191,132 @@ fn match_prop_change(
                 Some(rule_to) => rule_to.evaluate(to),
                 None => true,
             };
-            if !(from && to) {
-                return false;
-            }
-            match_whilecondition_set(r#while.as_ref(), devices)
+            from && to
         }
         _ => false,
     }
 }
+
+/// Cancels the pending hold periods of all properties of the device. Used when the device is removed or
+/// becomes unavailable, its properties do not hold their values anymore in that case.
+pub(crate) fn cancel_device_holds(device: &DeviceRef, desc: Option<&HomieDeviceDescription>, ctx: &RuleContext<'_>) {
+    let Some(desc) = desc else {
+        return;
+    };
+    for (hash, rule) in ctx.rules.iter() {
+        for (trigger_index, trigger) in rule.triggers.iter().enumerate() {
+            let (RuleTrigger::PropertyTriggered {
+                properties,
+                queries,
+                r#for: Some(_),
+                ..
+            }
+            | RuleTrigger::PropertyChanged {
+                properties,
+                queries,
+                r#for: Some(_),
+                ..
+            }) = trigger
+            else {
+                continue;
+            };
+            for (node_id, _, prop_id, _) in desc.iter() {
+                let prop = PropertyRef::new(
+                    device.homie_domain().clone(),
+                    device.device_id().clone(),
+                    node_id.clone(),
+                    prop_id.clone(),
+                );
+                if match_prop_subject(&prop, properties, queries) {
+                    ctx.deferred.cancel(&hold_id(*hash, trigger_index, &prop));
+                }
+            }
+        }
+    }
+}
+
+/// Starts or cancels the hold periods of all `PropertyTriggered` triggers with a `for` option.
+/// A hold is started by the first matching value and cancelled by the first value that does
+/// not match the `trigger_value` anymore.
+fn update_prop_trigger_holds(
+    rule_hash: ConfigItemHash,
+    rule: &Rule,
+    prop: &PropertyRef,
+    value: &HomieValue,
+    trigger_event: &RuleTriggerEvent<'_>,
+    ctx: &RuleContext<'_>,
+) {
+    for (trigger_index, trigger) in rule.triggers.iter().enumerate() {
+        let RuleTrigger::PropertyTriggered {
+            properties,
+            queries,
+            trigger_value,
+            r#for: Some(hold),
+            ..
+        } = trigger
+        else {
+            continue;
+        };
+        if !match_prop_subject(prop, properties, queries) {
+            continue;
+        }
+        let id = hold_id(rule_hash, trigger_index, prop);
+        if !trigger_value.evaluate(value) {
+            ctx.deferred.cancel(&id);
+        } else if !ctx.deferred.is_scheduled(&id) {
+            log::debug!("{} -- hold period started for {}", rule.name, prop.to_topic().build());
+            ctx.deferred
+                .schedule(rule_hash, trigger_index, id, *hold, trigger_event);
+        }
+    }
+}
+
+/// Starts or cancels the hold periods of all `PropertyChanged` triggers with a `for` option.
+/// A hold is started by a matching change and is cancelled as soon as the value does not satisfy
+/// `changed.to` anymore. Without a `to` condition every change restarts the hold period.
+fn update_prop_change_holds(
+    rule_hash: ConfigItemHash,
+    rule: &Rule,
+    prop: &PropertyRef,
+    from: Option<&HomieValue>,
+    to: &HomieValue,
+    trigger_event: &RuleTriggerEvent<'_>,
+    ctx: &RuleContext<'_>,
+) {
+    for (trigger_index, trigger) in rule.triggers.iter().enumerate() {
+        let RuleTrigger::PropertyChanged {
+            properties,
+            queries,
+            changed,
+            r#for: Some(hold),
+            ..
+        } = trigger
+        else {
+            continue;
+        };
+        if !match_prop_subject(prop, properties, queries) {
+            continue;
+        }
+        let id = hold_id(rule_hash, trigger_index, prop);
+        match &changed.to {
+            Some(rule_to) if !rule_to.evaluate(to) => {
+                ctx.deferred.cancel(&id);
+                continue;
+            }
+            // value still satisfies the condition, keep the running hold period
+            Some(_) if ctx.deferred.is_scheduled(&id) => continue,
+            Some(_) => {}
+            None => ctx.deferred.cancel(&id),
+        }
+        let from_matched = match (from, &changed.from) {
+            (Some(from), Some(rule_from)) => rule_from.evaluate(from),
+            (_, None) => true,
+            _ => false,
+        };
+        if from_matched {
+            log::debug!("{} -- hold period started for {}", rule.name, prop.to_topic().build());
+            ctx.deferred
+                .schedule(rule_hash, trigger_index, id, *hold, trigger_event);
+        }
+    }
+}
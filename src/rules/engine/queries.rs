@@ -7,7 +7,7 @@ use homie5::DeviceRef;
 
 pub fn queries_init_materialized(rule: &mut Rule, devices: &DeviceStore, vd: &HashMap<DeviceRef, VirtualDevice>) {
     for trigger in rule.triggers.iter_mut() {
-        match trigger {
+        trigger.visit_mut(&mut |trigger| match trigger {
             crate::rules::RuleTrigger::PropertyTriggered { ref mut queries, .. } => {
                 for query in queries.iter_mut() {
                     for (domain, id, device) in devices.iter() {
@@ -17,7 +17,10 @@ pub fn queries_init_materialized(rule: &mut Rule, devices: &DeviceStore, vd: &Ha
                     }
                 }
             }
-            crate::rules::RuleTrigger::PropertyChanged { ref mut queries, .. } => {
+            crate::rules::RuleTrigger::PropertyChanged { ref mut queries, .. }
+            | crate::rules::RuleTrigger::PropertyStale { ref mut queries, .. }
+            | crate::rules::RuleTrigger::PropertyCrossed { ref mut queries, .. }
+            | crate::rules::RuleTrigger::PropertyRate { ref mut queries, .. } => {
                 for query in queries.iter_mut() {
                     for (domain, id, device) in devices.iter() {
                         if let Some(desc) = device.description.as_ref() {
@@ -38,7 +41,7 @@ pub fn queries_init_materialized(rule: &mut Rule, devices: &DeviceStore, vd: &Ha
             }
 
             _ => {}
-        }
+        });
     }
 }
 // pub fn queries_remove_init_materialized(rule: &mut Rule, devices: &DeviceStore) {
@@ -0,0 +1,117 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use homie5::{HomieValue, PropertyRef, ToTopic};
+
+use super::{fire_trigger, match_prop_subject, numeric_value, while_condition::match_whilecondition_set, RuleContext};
+use crate::{
+    rules::{RateTrigger, RuleTrigger, RuleTriggerEvent},
+    trigger_state::{TriggerState, TriggerStateKey, TriggerStateStore},
+};
+
+/// Runs the rules with a rate trigger for the property whose value changed faster than the configured bounds
+pub async fn run_rate_rules(
+    prop: &PropertyRef,
+    value: &HomieValue,
+    trigger_event: &RuleTriggerEvent<'_>,
+    ctx: &RuleContext<'_>,
+) {
+    let Some(value) = numeric_value(value) else {
+        return;
+    };
+    let devices = ctx.dm.read().await;
+    let mut fired = Vec::new();
+    for (hash, rule) in ctx.rules.iter() {
+        let mut fired_index = None;
+        // all triggers need to be evaluated to keep their value history up to date
+        for (trigger_index, trigger) in rule.triggers.iter().enumerate() {
+            let RuleTrigger::PropertyRate {
+                properties,
+                queries,
+                rate,
+                r#while,
+                ..
+            } = trigger
+            else {
+                continue;
+            };
+            if !match_prop_subject(prop, properties, queries) {
+                continue;
+            }
+            let key = TriggerStateKey::new(*hash, trigger_index, prop.to_topic().build());
+            if match_rate(key, rate, value, ctx.trigger_states)
+                && match_whilecondition_set(r#while.as_ref(), &devices, ctx)
+            {
+                fired_index.get_or_insert(trigger_index);
+            }
+        }
+        if let Some(trigger_index) = fired_index {
+            fired.push((*hash, rule, trigger_index));
+        }
+    }
+    drop(devices);
+
+    for (hash, rule, trigger_index) in fired {
+        fire_trigger(hash, rule, trigger_index, trigger_event.to_owned(), ctx).await;
+    }
+}
+
+/// Adds the value to the history of the property and checks if the value rose or fell faster than
+/// the bounds of the trigger within its window. The history starts over after the trigger fired.
+fn match_rate(key: TriggerStateKey, rate: &RateTrigger, value: f64, states: &TriggerStateStore) -> bool {
+    states.update(key, |state| {
+        let now = Instant::now();
+        let mut history = match state.take() {
+            Some(TriggerState::Rate { history }) => history,
+            _ => VecDeque::new(),
+        };
+        while history
+            .front()
+            .is_some_and(|(received, _)| now.duration_since(*received) > rate.window)
+        {
+            history.pop_front();
+        }
+
+        let (rose, fell) = match (rate.per, history.front()) {
+            // the slope is measured against the oldest value in the window, so that it covers the configured
+            // period and small changes right after a new lowest or highest value are not taken as a steep slope
+            (Some(per), Some((since, oldest))) => {
+                let elapsed = now.duration_since(*since);
+                (
+                    rate.rise
+                        .is_some_and(|rise| exceeds_slope(value - oldest, elapsed, rise, per)),
+                    rate.fall
+                        .is_some_and(|fall| exceeds_slope(oldest - value, elapsed, fall, per)),
+                )
+            }
+            // compare against the lowest (for rise) and highest (for fall) value within the window
+            (None, Some(_)) => {
+                let lowest = history.iter().map(|(_, value)| *value).min_by(f64::total_cmp);
+                let highest = history.iter().map(|(_, value)| *value).max_by(f64::total_cmp);
+                (
+                    rate.rise
+                        .zip(lowest)
+                        .is_some_and(|(rise, lowest)| value - lowest >= rise),
+                    rate.fall
+                        .zip(highest)
+                        .is_some_and(|(fall, highest)| highest - value >= fall),
+                )
+            }
+            (_, None) => (false, false),
+        };
+
+        if rose || fell {
+            history.clear();
+        }
+        history.push_back((now, value));
+        *state = Some(TriggerState::Rate { history });
+        rose || fell
+    })
+}
+
+/// Checks the change of a value within the elapsed time as slope per `per` against the bound
+fn exceeds_slope(delta: f64, elapsed: Duration, bound: f64, per: Duration) -> bool {
+    delta > 0.0 && !elapsed.is_zero() && delta / elapsed.as_secs_f64() * per.as_secs_f64() >= bound
+}
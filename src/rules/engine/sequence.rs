@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant};
+
+use hc_homie5::store::DeviceStore;
+
+use super::{match_trigger_event, while_condition::match_whilecondition_set, RuleContext};
+use crate::{
+    rules::{RuleTrigger, RuleTriggerEvent},
+    trigger_state::{TriggerState, TriggerStateKey},
+};
+
+/// Advances the state of a sequence trigger with the event. Returns true once the last step of the
+/// sequence matched within the time window. The `while` condition is only evaluated for the step
+/// which matched the event, a step whose condition does not hold leaves the sequence unchanged.
+pub(crate) fn match_sequence(
+    key: TriggerStateKey,
+    sequence: &[RuleTrigger],
+    within: Duration,
+    event: &RuleTriggerEvent<'_>,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
+    if sequence.is_empty() {
+        return false;
+    }
+    ctx.trigger_states.update(key, |state| {
+        // a sequence which exceeded its time window starts over
+        let (mut step, mut started) = match state {
+            Some(TriggerState::Sequence { step, started }) if started.elapsed() <= within => (*step, *started),
+            _ => (0, Instant::now()),
+        };
+
+        let holds = |trigger: &RuleTrigger| match_whilecondition_set(trigger.while_condition(), devices, ctx);
+        if step > 0 && match_trigger_event(&sequence[step], event) {
+            if !holds(&sequence[step]) {
+                return false;
+            }
+            step += 1;
+        } else if match_trigger_event(&sequence[0], event) {
+            if !holds(&sequence[0]) {
+                return false;
+            }
+            // (re)start the sequence
+            step = 1;
+            started = Instant::now();
+        } else if sequence.iter().any(|trigger| match_trigger_event(trigger, event)) {
+            // a step out of order breaks the sequence
+            *state = None;
+            return false;
+        } else {
+            // the event is not part of the sequence
+            return false;
+        }
+
+        if step >= sequence.len() {
+            *state = None;
+            true
+        } else {
+            *state = Some(TriggerState::Sequence { step, started });
+            false
+        }
+    })
+}
@@ -0,0 +1,110 @@
+use std::borrow::Cow;
+
+use chrono::Utc;
+use config_watcher::ConfigItemHash;
+use hc_homie5::store::DeviceStore;
+use homie5::{HomieValue, PropertyRef, ToTopic};
+
+use super::match_prop_subject;
+use crate::{
+    deferred_trigger_manager::DeferredTriggerManager,
+    rules::{Rule, RuleTrigger, RuleTriggerEvent},
+};
+
+/// Builds the id used to track the stale watchdog of a single property for a rule trigger
+fn stale_id(rule_hash: ConfigItemHash, trigger_index: usize, prop: &PropertyRef) -> String {
+    format!("stale-{}-{}-{}", rule_hash, trigger_index, prop.to_topic().build())
+}
+
+/// Arms the stale watchdogs for all properties of the rule which are known at the time the rule is added.
+/// Properties which already have a value only get the remaining time until they are considered stale.
+pub fn arm_stale_triggers(
+    rule_hash: ConfigItemHash,
+    rule: &Rule,
+    devices: &DeviceStore,
+    deferred: &DeferredTriggerManager,
+) {
+    for (trigger_index, trigger) in rule.triggers.iter().enumerate() {
+        let RuleTrigger::PropertyStale {
+            properties,
+            queries,
+            stale_after,
+            ..
+        } = trigger
+        else {
+            continue;
+        };
+
+        let mut props = properties.clone();
+        if !queries.is_empty() {
+            for (domain, id, device) in devices.iter() {
+                let Some(desc) = device.description.as_ref() else {
+                    continue;
+                };
+                for (node_id, _, prop_id, _) in desc.iter() {
+                    let prop = PropertyRef::new(domain.clone(), id.clone(), node_id.clone(), prop_id.clone());
+                    if !props.contains(&prop) && queries.iter().any(|query| query.match_query(&prop)) {
+                        props.push(prop);
+                    }
+                }
+            }
+        }
+
+        for prop in props {
+            let entry = devices.get_value_entry(&prop);
+            let last_received = entry.and_then(|entry| entry.value_last_received);
+            let elapsed = last_received
+                .and_then(|last_received| (Utc::now() - last_received).to_std().ok())
+                .unwrap_or_default();
+            let trigger_event = RuleTriggerEvent::PropertyStale {
+                prop: Cow::Borrowed(&prop),
+                last_value: Cow::Owned(entry.and_then(|entry| entry.value.clone())),
+                last_received,
+            };
+            deferred.schedule(
+                rule_hash,
+                trigger_index,
+                stale_id(rule_hash, trigger_index, &prop),
+                stale_after.saturating_sub(elapsed),
+                &trigger_event,
+            );
+        }
+    }
+}
+
+/// Re-arms the stale watchdogs of all `PropertyStale` triggers of the rule matching the property
+/// that just published a new value.
+pub(crate) fn rearm_stale_triggers(
+    rule_hash: ConfigItemHash,
+    rule: &Rule,
+    prop: &PropertyRef,
+    value: &HomieValue,
+    deferred: &DeferredTriggerManager,
+) {
+    for (trigger_index, trigger) in rule.triggers.iter().enumerate() {
+        let RuleTrigger::PropertyStale {
+            properties,
+            queries,
+            stale_after,
+            ..
+        } = trigger
+        else {
+            continue;
+        };
+        if !match_prop_subject(prop, properties, queries) {
+            continue;
+        }
+        let trigger_event = RuleTriggerEvent::PropertyStale {
+            prop: Cow::Borrowed(prop),
+            last_value: Cow::Owned(Some(value.clone())),
+            last_received: Some(Utc::now()),
+        };
+        deferred.schedule(
+            rule_hash,
+            trigger_index,
+            stale_id(rule_hash, trigger_index, prop),
+            *stale_after,
+            &trigger_event,
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use super::{fire_trigger, while_condition::match_whilecondition_set, RuleContext};
+use crate::{rules::RuleTrigger, system_events::SystemEvent};
+use hc_homie5::store::DeviceStore;
+
+pub async fn run_system_rules(event: &SystemEvent, ctx: &RuleContext<'_>) {
+    let devices = &ctx.dm.read().await;
+    for (hash, rule) in ctx.rules.iter() {
+        let Some(trigger_index) = rule
+            .triggers
+            .iter()
+            .position(|trigger| match_system_event(event, trigger, devices, ctx))
+        else {
+            continue;
+        };
+        fire_trigger(*hash, rule, trigger_index, event.into(), ctx).await;
+    }
+}
+
+fn match_system_event(
+    event: &SystemEvent,
+    trigger: &RuleTrigger,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
+    match trigger {
+        RuleTrigger::SystemTrigger { system, r#while, .. } => {
+            system.evaluate(&event.kind) && match_whilecondition_set(r#while.as_ref(), devices, ctx)
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,47 @@
+use super::{fire_trigger, while_condition::match_whilecondition_set, RuleContext};
+use crate::{
+    rules::RuleTrigger,
+    value_store_events::{match_key_pattern, ValueStoreEvent},
+};
+use hc_homie5::store::DeviceStore;
+
+pub async fn run_value_store_rules(event: &ValueStoreEvent, ctx: &RuleContext<'_>) {
+    let devices = &ctx.dm.read().await;
+    for (hash, rule) in ctx.rules.iter() {
+        let Some(trigger_index) = rule
+            .triggers
+            .iter()
+            .position(|trigger| match_value_store_event(event, trigger, devices, ctx))
+        else {
+            continue;
+        };
+        fire_trigger(*hash, rule, trigger_index, event.into(), ctx).await;
+    }
+}
+
+fn match_value_store_event(
+    event: &ValueStoreEvent,
+    trigger: &RuleTrigger,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
+    match trigger {
+        RuleTrigger::ValueStoreTrigger {
+            value_store,
+            trigger_value,
+            r#while,
+            ..
+        } => {
+            if !match_key_pattern(value_store, &event.key) {
+                return false;
+            }
+            if let Some(trigger_value) = trigger_value {
+                if !trigger_value.evaluate_option(event.value_string().as_ref()) {
+                    return false;
+                }
+            }
+            match_whilecondition_set(r#while.as_ref(), devices, ctx)
+        }
+        _ => false,
+    }
+}
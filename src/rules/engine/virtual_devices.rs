@@ -2,30 +2,48 @@ use crate::rules::RuleTrigger;
 use hc_homie5::store::DeviceStore;
 use homie5::{Homie5Message, PropertyRef};
 
-use super::{run_rule_actions, while_condition::match_whilecondition_set, RuleContext};
+use super::{fire_trigger, run_composite_rules, while_condition::match_whilecondition_set, RuleContext};
 
 pub async fn run_on_set_rules(event: &Homie5Message, ctx: &RuleContext<'_>) {
     if let Homie5Message::PropertySet { property, set_value } = event {
+        if let Ok(trigger_event) = event.try_into() {
+            run_composite_rules(&trigger_event, ctx).await;
+        }
         let devices = ctx.dm.read().await;
-        for (hash, rule) in ctx.rules.iter().filter(|(_, rule)| {
-            rule.triggers
+        for (hash, rule) in ctx.rules.iter() {
+            let Some(trigger_index) = rule
+                .triggers
                 .iter()
-                .any(|trigger| match_prop_set(property, set_value, trigger, &devices))
-        }) {
+                .position(|trigger| match_prop_set(property, set_value, trigger, &devices, ctx))
+            else {
+                continue;
+            };
             if let Ok(event) = event.try_into() {
-                run_rule_actions(*hash, rule, event, ctx).await;
+                fire_trigger(*hash, rule, trigger_index, event, ctx).await;
             }
         }
     };
 }
 
-fn match_prop_set(prop: &PropertyRef, on_set_value: &String, trigger: &RuleTrigger, devices: &DeviceStore) -> bool {
+pub(crate) fn match_prop_set(
+    prop: &PropertyRef,
+    on_set_value: &String,
+    trigger: &RuleTrigger,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
+    match_prop_set_event(prop, on_set_value, trigger)
+        && match_whilecondition_set(trigger.while_condition(), devices, ctx)
+}
+
+/// Checks if the set value matches the on set trigger, without evaluating its `while` condition
+pub(crate) fn match_prop_set_event(prop: &PropertyRef, on_set_value: &String, trigger: &RuleTrigger) -> bool {
     match trigger {
         RuleTrigger::OnSetEventTrigger {
             properties: on_set_properties,
             queries: on_set_queries,
             set_value,
-            r#while,
+            ..
         } => {
             // Check if either properties or queries are non-empty
             let properties_match = !on_set_properties.is_empty() && on_set_properties.iter().any(|p| p == prop);
@@ -42,11 +60,7 @@ fn match_prop_set(prop: &PropertyRef, on_set_value: &String, trigger: &RuleTrigg
                 return false;
             }
 
-            if !set_value.evaluate(on_set_value) {
-                return false;
-            }
-
-            match_whilecondition_set(r#while.as_ref(), devices)
+            set_value.evaluate(on_set_value)
         }
         _ => false,
     }
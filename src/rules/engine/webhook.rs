@@ -0,0 +1,106 @@
+use super::{fire_trigger, mqtt_topic_match, while_condition::match_whilecondition_set, RuleContext};
+use crate::{
+    rules::RuleTrigger,
+    webhook::{WebhookEvent, WebhookRequest, WebhookResponse},
+};
+use hc_homie5::store::DeviceStore;
+
+/// Evaluates the webhook triggers of all rules for the request, answers the http request and runs the
+/// actions of the matching rules afterwards (the client does not wait for the actions to complete).
+pub async fn run_webhook_rules(event: WebhookEvent, ctx: &RuleContext<'_>) {
+    let WebhookEvent {
+        request,
+        secret,
+        response,
+    } = event;
+    let mut result = WebhookResponse::NotFound;
+    let mut matched_rules = Vec::new();
+    {
+        let devices = &ctx.dm.read().await;
+        for (hash, rule) in ctx.rules.iter() {
+            let mut matched_index = None;
+            for (trigger_index, trigger) in rule.triggers.iter().enumerate() {
+                match match_webhook(&request, secret.as_deref(), trigger, devices, ctx) {
+                    Some(WebhookMatch::Matched) => {
+                        result = WebhookResponse::Accepted;
+                        matched_index.get_or_insert(trigger_index);
+                    }
+                    Some(WebhookMatch::Routed) => result = WebhookResponse::Accepted,
+                    Some(WebhookMatch::Unauthorized) if result == WebhookResponse::NotFound => {
+                        result = WebhookResponse::Unauthorized
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(trigger_index) = matched_index {
+                matched_rules.push((*hash, rule, trigger_index));
+            }
+        }
+    }
+    if result != WebhookResponse::Accepted {
+        log::debug!("Webhook request {} {} not accepted: {:?}", request.method, request.path, result);
+    }
+    let _ = response.send(result);
+
+    for (hash, rule, trigger_index) in matched_rules {
+        fire_trigger(hash, rule, trigger_index, (&request).into(), ctx).await;
+    }
+}
+
+enum WebhookMatch {
+    /// path, method and secret match and the while conditions are met
+    Matched,
+    /// path, method and secret match, but the while conditions are not met
+    Routed,
+    /// path and method match, but the secret is missing or wrong
+    Unauthorized,
+}
+
+fn match_webhook(
+    request: &WebhookRequest,
+    provided_secret: Option<&str>,
+    trigger: &RuleTrigger,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> Option<WebhookMatch> {
+    match trigger {
+        RuleTrigger::WebhookTrigger {
+            webhook,
+            method,
+            secret,
+            r#while,
+            ..
+        } => {
+            if !mqtt_topic_match(webhook.trim_matches('/'), request.path.trim_matches('/')) {
+                return None;
+            }
+            if !method
+                .as_ref()
+                .is_none_or(|method| method.eq_ignore_ascii_case(&request.method))
+            {
+                return None;
+            }
+            if let Some(secret) = secret {
+                if !provided_secret.is_some_and(|provided| secret_eq(secret, provided)) {
+                    return Some(WebhookMatch::Unauthorized);
+                }
+            }
+            if match_whilecondition_set(r#while.as_ref(), devices, ctx) {
+                Some(WebhookMatch::Matched)
+            } else {
+                Some(WebhookMatch::Routed)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Compares the secrets in constant time (for secrets of equal length)
+fn secret_eq(expected: &str, provided: &str) -> bool {
+    expected.len() == provided.len()
+        && expected
+            .bytes()
+            .zip(provided.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
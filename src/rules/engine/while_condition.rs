@@ -1,17 +1,25 @@
-use crate::rules::{WhileCondition, WhileConditionSet};
+use super::RuleContext;
+use crate::{
+    rules::{WhileCondition, WhileConditionSet},
+    value_store_events::value_string,
+};
 use hc_homie5::store::DeviceStore;
 
-pub(crate) fn match_whilecondition_set(while_condition_set: Option<&WhileConditionSet>, devices: &DeviceStore) -> bool {
+pub(crate) fn match_whilecondition_set(
+    while_condition_set: Option<&WhileConditionSet>,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
     if let Some(while_conditions) = while_condition_set {
         match while_conditions {
             WhileConditionSet::Single(while_condition) => {
-                if !match_whilecondition(while_condition, devices) {
+                if !match_whilecondition(while_condition, devices, ctx) {
                     return false;
                 }
                 true
             }
             WhileConditionSet::Multiple(vec) => {
-                if vec.iter().any(|cond| !match_whilecondition(cond, devices)) {
+                if vec.iter().any(|cond| !match_whilecondition(cond, devices, ctx)) {
                     return false;
                 }
                 true
@@ -22,7 +30,11 @@ pub(crate) fn match_whilecondition_set(while_condition_set: Option<&WhileConditi
     }
 }
 
-pub(crate) fn match_whilecondition(while_condition: &WhileCondition, devices: &DeviceStore) -> bool {
+pub(crate) fn match_whilecondition(
+    while_condition: &WhileCondition,
+    devices: &DeviceStore,
+    ctx: &RuleContext<'_>,
+) -> bool {
     match while_condition {
         WhileCondition::PropertyWhileCondition(property_while_condition) => devices
             .get_device(property_while_condition.property.device_ref())
@@ -33,6 +45,34 @@ pub(crate) fn match_whilecondition(while_condition: &WhileCondition, devices: &D
                     .and_then(|prop_value_entry| prop_value_entry.value.as_ref())
             })
             .is_some_and(|value| property_while_condition.condition.evaluate(value)),
-        WhileCondition::TimeWhileCondition(time_while_condition) => time_while_condition.evaluate(),
+        WhileCondition::TimeWhileCondition(time_while_condition) => {
+            time_while_condition.evaluate(ctx.calendars, ctx.solar_events.location())
+        }
+        WhileCondition::ValueStoreWhileCondition(value_store_while_condition) => {
+            let value = ctx.value_store_events.value(&value_store_while_condition.key());
+            value_store_while_condition
+                .condition
+                .evaluate_option(value.as_ref().map(value_string).as_ref())
+        }
+        WhileCondition::TimerWhileCondition(timer_while_condition) => {
+            ctx.timers.is_active(&timer_while_condition.timer_active)
+        }
+        WhileCondition::DeviceStateWhileCondition(device_state_while_condition) => devices
+            .device_state_resolved(&device_state_while_condition.device)
+            .is_some_and(|state| device_state_while_condition.state.evaluate(&state)),
+        WhileCondition::ValueAgeWhileCondition(value_age_while_condition) => devices
+            .get_value_entry(&value_age_while_condition.property)
+            .is_some_and(|entry| {
+                value_age_while_condition.evaluate(entry.value_last_received, entry.value_last_changed)
+            }),
+        WhileCondition::AnyOfWhileCondition(any_of) => any_of
+            .any_of
+            .iter()
+            .any(|cond| match_whilecondition(cond, devices, ctx)),
+        WhileCondition::AllOfWhileCondition(all_of) => all_of
+            .all_of
+            .iter()
+            .all(|cond| match_whilecondition(cond, devices, ctx)),
+        WhileCondition::NotWhileCondition(not) => !match_whilecondition_set(Some(&not.not), devices, ctx),
     }
 }
@@ -1,4 +1,4 @@
-use super::TimerDef;
+use super::{BinaryEncoding, TimerDef};
 use crate::solar_events::SolarPhase;
 use hc_homie5::value::{ValueMappingList, ValueMatcher};
 use homie5::PropertyRef;
@@ -35,11 +35,16 @@ pub enum RuleAction {
     Mqtt {
         topic: String,
         value: String,
+        /// encoding of a binary payload in `value`
+        #[serde(default)]
+        binary: Option<BinaryEncoding>,
         #[serde(default)]
         qos: QoS,
         #[serde(default)]
         retain: bool,
     },
+    #[serde(rename = "broadcast")]
+    Broadcast { subtopic: String, value: String },
 }
 
 #[derive(Debug, Clone, Deserialize, PartialEq, PartialOrd)]
@@ -0,0 +1,47 @@
+use hc_homie5::query::DeviceQuery;
+use hc_homie5::value::ValueCondition;
+use homie5::{device_description::HomieDeviceDescription, DeviceRef, HomieDeviceStatus, HomieDomain};
+use serde::Deserialize;
+
+/// Query matching whole devices (in contrast to property queries which match properties)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceQueryDefinition {
+    #[serde(default)]
+    pub domain: Option<ValueCondition<HomieDomain>>,
+    #[serde(default)]
+    pub device: Option<DeviceQuery>,
+}
+
+impl DeviceQueryDefinition {
+    /// Matches the device against the query. Device attribute queries require the device description to be known.
+    pub fn match_query(&self, device: &DeviceRef, desc: Option<&HomieDeviceDescription>) -> bool {
+        let domain_match = self
+            .domain
+            .as_ref()
+            .is_none_or(|cond| matches!(cond.value(), Some(HomieDomain::All)) || cond.evaluate(device.homie_domain()));
+        let device_match = match (&self.device, desc) {
+            (None, _) => true,
+            (Some(query), Some(desc)) => query.match_query(device.device_id(), desc),
+            (Some(_), None) => false,
+        };
+        domain_match && device_match
+    }
+}
+
+/// Condition on a device state, either a single state or a list of states of which one needs to match
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum DeviceStatusCondition {
+    Single(HomieDeviceStatus),
+    Multiple(Vec<HomieDeviceStatus>),
+}
+
+impl DeviceStatusCondition {
+    pub fn evaluate(&self, status: &HomieDeviceStatus) -> bool {
+        match self {
+            DeviceStatusCondition::Single(s) => s == status,
+            DeviceStatusCondition::Multiple(list) => list.contains(status),
+        }
+    }
+}
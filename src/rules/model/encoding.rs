@@ -0,0 +1,65 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::eyre::{eyre, Result};
+use hc_homie5::value::{ValueCondition, ValueSet};
+use serde::Deserialize;
+
+/// Text encoding of binary mqtt payloads in rule configs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryEncoding {
+    Hex,
+    Base64,
+}
+
+impl BinaryEncoding {
+    pub fn encode(&self, data: &[u8]) -> String {
+        match self {
+            BinaryEncoding::Hex => data.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            BinaryEncoding::Base64 => STANDARD.encode(data),
+        }
+    }
+
+    pub fn decode(&self, value: &str) -> Result<Vec<u8>> {
+        match self {
+            BinaryEncoding::Hex => {
+                let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+                if !value.is_ascii() || !value.len().is_multiple_of(2) {
+                    return Err(eyre!("Invalid hex value [{}]: expected pairs of hex digits", value));
+                }
+                (0..value.len())
+                    .step_by(2)
+                    .map(|i| {
+                        u8::from_str_radix(&value[i..i + 2], 16)
+                            .map_err(|err| eyre!("Invalid hex value [{}]: {}", value, err))
+                    })
+                    .collect()
+            }
+            BinaryEncoding::Base64 => STANDARD
+                .decode(value.trim())
+                .map_err(|err| eyre!("Invalid base64 value [{}]: {}", value, err)),
+        }
+    }
+
+    /// Decodes the value and encodes it again, e.g. upper case hex digits become lower case like in encoded payloads
+    pub fn normalize(&self, value: &str) -> Result<String> {
+        Ok(self.encode(&self.decode(value)?))
+    }
+
+    /// Normalizes the values a condition compares encoded payloads with. Patterns are kept as they are.
+    pub fn normalize_condition(&self, condition: &mut ValueCondition<String>) -> Result<()> {
+        match condition {
+            ValueCondition::Value(value) => *value = self.normalize(value)?,
+            ValueCondition::Operator(operator) => match &mut operator.value {
+                Some(ValueSet::Single(value)) => *value = self.normalize(value)?,
+                Some(ValueSet::Multiple(values)) => {
+                    for value in values.iter_mut() {
+                        *value = self.normalize(value)?;
+                    }
+                }
+                None => {}
+            },
+            ValueCondition::Pattern(_) => {}
+        }
+        Ok(())
+    }
+}
@@ -1,24 +1,103 @@
 mod action;
+mod device;
+mod encoding;
 mod timer;
 mod trigger;
 mod while_cond;
 
 use std::time::Duration;
 
+use chrono::TimeDelta;
+
 pub use action::*;
+pub use device::*;
+pub use encoding::*;
 use serde::{de::Visitor, Deserialize, Deserializer};
 pub use timer::*;
 pub use trigger::*;
 pub use while_cond::*;
 
 #[derive(Debug, Clone, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[serde(try_from = "RuleDef")]
 pub struct Rule {
     pub name: String,
     pub triggers: Vec<RuleTrigger>,
     pub actions: Vec<RuleAction>,
 }
 
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDef {
+    name: String,
+    triggers: Vec<RuleTrigger>,
+    actions: Vec<RuleAction>,
+}
+
+impl TryFrom<RuleDef> for Rule {
+    type Error = String;
+
+    /// Checks the binary mqtt payloads of the rule, so that invalid values fail when the rule is loaded
+    fn try_from(value: RuleDef) -> Result<Self, Self::Error> {
+        let RuleDef {
+            name,
+            mut triggers,
+            actions,
+        } = value;
+        let mut result = Ok(());
+        for trigger in triggers.iter_mut() {
+            trigger.visit_mut(&mut |trigger| {
+                if let RuleTrigger::MqttTrigger {
+                    binary: Some(encoding),
+                    trigger_value,
+                    ..
+                } = trigger
+                {
+                    if let Err(err) = encoding.normalize_condition(trigger_value) {
+                        result = Err(err);
+                    }
+                }
+            });
+        }
+        for action in &actions {
+            if let RuleAction::Mqtt {
+                value,
+                binary: Some(encoding),
+                ..
+            } = action
+            {
+                if let Err(err) = encoding.decode(value) {
+                    result = Err(err);
+                }
+            }
+        }
+        result.map_err(|err| format!("Rule [{}]: {}", name, err))?;
+        Ok(Rule {
+            name,
+            triggers,
+            actions,
+        })
+    }
+}
+
+impl Rule {
+    /// Returns the (normalized) value store keys checked by the while conditions of the rule
+    pub fn while_value_store_keys(&self) -> Vec<String> {
+        let mut keys = Vec::new();
+        for trigger in &self.triggers {
+            trigger.visit(&mut |trigger| {
+                if let Some(while_condition) = trigger.while_condition() {
+                    while_condition.visit(&mut |condition| {
+                        if let WhileCondition::ValueStoreWhileCondition(condition) = condition {
+                            keys.push(condition.key());
+                        }
+                    });
+                }
+            });
+        }
+        keys
+    }
+}
+
 struct DurationVisitor;
 
 impl Visitor<'_> for DurationVisitor {
@@ -84,6 +163,22 @@ where
     deserializer.deserialize_string(DurationVisitor)
 }
 
+/// Deserializes an optional signed duration string (e.g. `-30m`, `+10m`, `15s`)
+pub fn deserialize_optional_offset<'de, D>(deserializer: D) -> Result<Option<TimeDelta>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(value) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('+').unwrap_or(&value)),
+    };
+    let offset = TimeDelta::from_std(DurationVisitor.visit_str(value)?).map_err(serde::de::Error::custom)?;
+    Ok(Some(if negative { -offset } else { offset }))
+}
+
 pub fn deserialize_optional_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
 where
     D: Deserializer<'de>,
@@ -1,20 +1,31 @@
 use std::borrow::Cow;
 use std::time::Duration;
 
+use chrono::{DateTime, NaiveTime, TimeDelta, Utc};
+use chrono_tz::Tz;
 use color_eyre::eyre::{self, eyre};
 use hc_homie5::model::DiscoveryAction;
 use homie5::client::QoS;
-use homie5::{Homie5Message, HomieValue, PropertyRef};
+use homie5::{
+    device_description::HomieDeviceDescription, DeviceRef, Homie5Message, HomieDeviceStatus, HomieID, HomieValue,
+    PropertyRef,
+};
 use serde::Deserialize;
 
-use crate::cron_manager::CronEvent;
+use crate::cron_manager::{CronEvent, CronSchedule};
 use crate::mqtt_client::MqttPublishEvent;
 use crate::solar_events::{SolarEvent, SolarPhase};
+use crate::system_events::{SystemEvent, SystemEventKind};
 use crate::timer_manager::TimerEvent;
+use crate::value_store_events::ValueStoreEvent;
+use crate::webhook::WebhookRequest;
 use hc_homie5::query::MaterializedQuery;
 use hc_homie5::value::ValueCondition;
 
-use super::{deserialize_duration, WhileConditionSet};
+use super::{
+    deserialize_duration, deserialize_optional_duration, BinaryEncoding, DeviceQueryDefinition, DeviceStatusCondition,
+    WhileConditionSet,
+};
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged, deny_unknown_fields)]
@@ -25,7 +36,13 @@ pub enum RuleTrigger {
         #[serde(default)]
         queries: Vec<MaterializedQuery>,
         trigger_value: ValueCondition<HomieValue>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        r#for: Option<Duration>,
         r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
     },
     PropertyChanged {
         #[serde(default)]
@@ -33,15 +50,106 @@ pub enum RuleTrigger {
         #[serde(default)]
         queries: Vec<MaterializedQuery>,
         changed: ChangedTrigger,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        r#for: Option<Duration>,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    PropertyStale {
+        #[serde(default)]
+        properties: Vec<PropertyRef>,
+        #[serde(default)]
+        queries: Vec<MaterializedQuery>,
+        #[serde(deserialize_with = "deserialize_duration")]
+        stale_after: Duration,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    PropertyCrossed {
+        #[serde(default)]
+        properties: Vec<PropertyRef>,
+        #[serde(default)]
+        queries: Vec<MaterializedQuery>,
+        crossing: CrossingTrigger,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    PropertyRate {
+        #[serde(default)]
+        properties: Vec<PropertyRef>,
+        #[serde(default)]
+        queries: Vec<MaterializedQuery>,
+        rate: RateTrigger,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    DeviceStateChanged {
+        #[serde(default)]
+        devices: Vec<DeviceRef>,
+        #[serde(default)]
+        queries: Vec<DeviceQueryDefinition>,
+        device_state: DeviceStateTrigger,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    DeviceLifecycle {
+        #[serde(default)]
+        devices: Vec<DeviceRef>,
+        #[serde(default)]
+        queries: Vec<DeviceQueryDefinition>,
+        device_event: DeviceLifecycleEvent,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    DeviceAlert {
+        #[serde(default)]
+        devices: Vec<DeviceRef>,
+        #[serde(default)]
+        queries: Vec<DeviceQueryDefinition>,
+        alert: AlertTrigger,
         r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
     },
     TimerTrigger {
         timer_id: String,
         r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
     },
     CronTrigger {
-        schedule: String,
+        schedule: CronSchedule,
+        #[serde(default)]
+        timezone: Option<Tz>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        jitter: Option<Duration>,
         r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
     },
     MqttTrigger {
         topic: String,
@@ -53,24 +161,131 @@ pub enum RuleTrigger {
         check_qos: bool,
         #[serde(default)]
         qos: QoS,
+        #[serde(default)]
+        json_path: Option<String>,
+        #[serde(default)]
+        binary: Option<BinaryEncoding>,
         trigger_value: ValueCondition<String>,
         r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
     },
     SolarEventTriggerAfter {
         sun_phase: SolarPhase,
         #[serde(deserialize_with = "deserialize_duration")]
         min_after: Duration,
+        not_before: Option<NaiveTime>,
+        not_after: Option<NaiveTime>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        jitter: Option<Duration>,
         r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
     },
     SolarEventTriggerBefore {
         sun_phase: SolarPhase,
         #[serde(deserialize_with = "deserialize_duration")]
         min_before: Duration,
+        not_before: Option<NaiveTime>,
+        not_after: Option<NaiveTime>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        jitter: Option<Duration>,
         r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
     },
     SolarEventTrigger {
         sun_phase: SolarPhase,
+        not_before: Option<NaiveTime>,
+        not_after: Option<NaiveTime>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        jitter: Option<Duration>,
         r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    SequenceTrigger {
+        sequence: Vec<RuleTrigger>,
+        #[serde(deserialize_with = "deserialize_duration")]
+        within: Duration,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    AllOfTrigger {
+        all_of: Vec<RuleTrigger>,
+        #[serde(deserialize_with = "deserialize_duration")]
+        within: Duration,
+        #[serde(default)]
+        ordered: bool,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    CountTrigger {
+        count: Box<RuleTrigger>,
+        threshold: usize,
+        #[serde(deserialize_with = "deserialize_duration")]
+        window: Duration,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        cooldown: Option<Duration>,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    SystemTrigger {
+        system: SystemEventCondition,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    BroadcastTrigger {
+        broadcast: String,
+        #[serde(default)]
+        trigger_value: Option<ValueCondition<String>>,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    WebhookTrigger {
+        webhook: String,
+        #[serde(default)]
+        method: Option<String>,
+        #[serde(default)]
+        secret: Option<String>,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
+    },
+    ValueStoreTrigger {
+        value_store: String,
+        #[serde(default)]
+        trigger_value: Option<ValueCondition<String>>,
+        r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
     },
     OnSetEventTrigger {
         #[serde(default)]
@@ -79,9 +294,133 @@ pub enum RuleTrigger {
         queries: Vec<MaterializedQuery>,
         set_value: ValueCondition<String>,
         r#while: Option<WhileConditionSet>,
+        #[serde(default, deserialize_with = "deserialize_optional_duration")]
+        debounce: Option<Duration>,
+        #[serde(default)]
+        throttle: Option<ThrottleOptions>,
     },
 }
 
+impl RuleTrigger {
+    pub fn while_condition(&self) -> Option<&WhileConditionSet> {
+        match self {
+            RuleTrigger::PropertyTriggered { r#while, .. }
+            | RuleTrigger::PropertyChanged { r#while, .. }
+            | RuleTrigger::PropertyStale { r#while, .. }
+            | RuleTrigger::PropertyCrossed { r#while, .. }
+            | RuleTrigger::PropertyRate { r#while, .. }
+            | RuleTrigger::DeviceStateChanged { r#while, .. }
+            | RuleTrigger::DeviceLifecycle { r#while, .. }
+            | RuleTrigger::DeviceAlert { r#while, .. }
+            | RuleTrigger::TimerTrigger { r#while, .. }
+            | RuleTrigger::CronTrigger { r#while, .. }
+            | RuleTrigger::MqttTrigger { r#while, .. }
+            | RuleTrigger::SolarEventTriggerAfter { r#while, .. }
+            | RuleTrigger::SolarEventTriggerBefore { r#while, .. }
+            | RuleTrigger::SolarEventTrigger { r#while, .. }
+            | RuleTrigger::SystemTrigger { r#while, .. }
+            | RuleTrigger::ValueStoreTrigger { r#while, .. }
+            | RuleTrigger::BroadcastTrigger { r#while, .. }
+            | RuleTrigger::WebhookTrigger { r#while, .. }
+            | RuleTrigger::SequenceTrigger { r#while, .. }
+            | RuleTrigger::AllOfTrigger { r#while, .. }
+            | RuleTrigger::CountTrigger { r#while, .. }
+            | RuleTrigger::OnSetEventTrigger { r#while, .. } => r#while.as_ref(),
+        }
+    }
+
+    /// Returns the quiet period after which the trigger fires with the last matched event
+    pub fn debounce(&self) -> Option<Duration> {
+        match self {
+            RuleTrigger::PropertyTriggered { debounce, .. }
+            | RuleTrigger::PropertyChanged { debounce, .. }
+            | RuleTrigger::PropertyStale { debounce, .. }
+            | RuleTrigger::PropertyCrossed { debounce, .. }
+            | RuleTrigger::PropertyRate { debounce, .. }
+            | RuleTrigger::DeviceStateChanged { debounce, .. }
+            | RuleTrigger::DeviceLifecycle { debounce, .. }
+            | RuleTrigger::DeviceAlert { debounce, .. }
+            | RuleTrigger::TimerTrigger { debounce, .. }
+            | RuleTrigger::CronTrigger { debounce, .. }
+            | RuleTrigger::MqttTrigger { debounce, .. }
+            | RuleTrigger::SolarEventTriggerAfter { debounce, .. }
+            | RuleTrigger::SolarEventTriggerBefore { debounce, .. }
+            | RuleTrigger::SolarEventTrigger { debounce, .. }
+            | RuleTrigger::SystemTrigger { debounce, .. }
+            | RuleTrigger::ValueStoreTrigger { debounce, .. }
+            | RuleTrigger::BroadcastTrigger { debounce, .. }
+            | RuleTrigger::WebhookTrigger { debounce, .. }
+            | RuleTrigger::SequenceTrigger { debounce, .. }
+            | RuleTrigger::AllOfTrigger { debounce, .. }
+            | RuleTrigger::CountTrigger { debounce, .. }
+            | RuleTrigger::OnSetEventTrigger { debounce, .. } => *debounce,
+        }
+    }
+
+    /// Returns the options limiting how often the trigger fires
+    pub fn throttle(&self) -> Option<&ThrottleOptions> {
+        match self {
+            RuleTrigger::PropertyTriggered { throttle, .. }
+            | RuleTrigger::PropertyChanged { throttle, .. }
+            | RuleTrigger::PropertyStale { throttle, .. }
+            | RuleTrigger::PropertyCrossed { throttle, .. }
+            | RuleTrigger::PropertyRate { throttle, .. }
+            | RuleTrigger::DeviceStateChanged { throttle, .. }
+            | RuleTrigger::DeviceLifecycle { throttle, .. }
+            | RuleTrigger::DeviceAlert { throttle, .. }
+            | RuleTrigger::TimerTrigger { throttle, .. }
+            | RuleTrigger::CronTrigger { throttle, .. }
+            | RuleTrigger::MqttTrigger { throttle, .. }
+            | RuleTrigger::SolarEventTriggerAfter { throttle, .. }
+            | RuleTrigger::SolarEventTriggerBefore { throttle, .. }
+            | RuleTrigger::SolarEventTrigger { throttle, .. }
+            | RuleTrigger::SystemTrigger { throttle, .. }
+            | RuleTrigger::ValueStoreTrigger { throttle, .. }
+            | RuleTrigger::BroadcastTrigger { throttle, .. }
+            | RuleTrigger::WebhookTrigger { throttle, .. }
+            | RuleTrigger::SequenceTrigger { throttle, .. }
+            | RuleTrigger::AllOfTrigger { throttle, .. }
+            | RuleTrigger::CountTrigger { throttle, .. }
+            | RuleTrigger::OnSetEventTrigger { throttle, .. } => throttle.as_ref(),
+        }
+    }
+
+    /// Returns the triggers nested inside composite triggers (e.g. the steps of a sequence)
+    pub fn inner_triggers(&self) -> &[RuleTrigger] {
+        match self {
+            RuleTrigger::SequenceTrigger { sequence, .. } => sequence,
+            RuleTrigger::AllOfTrigger { all_of, .. } => all_of,
+            RuleTrigger::CountTrigger { count, .. } => std::slice::from_ref(count),
+            _ => &[],
+        }
+    }
+
+    pub fn inner_triggers_mut(&mut self) -> &mut [RuleTrigger] {
+        match self {
+            RuleTrigger::SequenceTrigger { sequence, .. } => sequence,
+            RuleTrigger::AllOfTrigger { all_of, .. } => all_of,
+            RuleTrigger::CountTrigger { count, .. } => std::slice::from_mut(count),
+            _ => &mut [],
+        }
+    }
+
+    /// Calls `f` for this trigger and all triggers nested inside of it
+    pub fn visit<'a>(&'a self, f: &mut impl FnMut(&'a RuleTrigger)) {
+        f(self);
+        for trigger in self.inner_triggers() {
+            trigger.visit(f);
+        }
+    }
+
+    /// Calls `f` for this trigger and all triggers nested inside of it
+    pub fn visit_mut(&mut self, f: &mut impl FnMut(&mut RuleTrigger)) {
+        f(self);
+        for trigger in self.inner_triggers_mut() {
+            trigger.visit_mut(f);
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub enum RuleTriggerEvent<'a> {
@@ -95,21 +434,79 @@ pub enum RuleTriggerEvent<'a> {
         prop: Cow<'a, PropertyRef>,
         value: Cow<'a, HomieValue>,
     },
+    PropertyStale {
+        prop: Cow<'a, PropertyRef>,
+        last_value: Cow<'a, Option<HomieValue>>,
+        last_received: Option<DateTime<Utc>>,
+    },
+    DeviceStateChanged {
+        device: Cow<'a, DeviceRef>,
+        from: HomieDeviceStatus,
+        to: HomieDeviceStatus,
+    },
+    DeviceAdded {
+        device: Cow<'a, DeviceRef>,
+        description: Cow<'a, Option<HomieDeviceDescription>>,
+    },
+    DeviceRemoved {
+        device: Cow<'a, DeviceRef>,
+        description: Cow<'a, Option<HomieDeviceDescription>>,
+    },
+    DeviceAlertRaised {
+        device: Cow<'a, DeviceRef>,
+        alert_id: Cow<'a, HomieID>,
+        message: Cow<'a, String>,
+    },
+    DeviceAlertCleared {
+        device: Cow<'a, DeviceRef>,
+        alert_id: Cow<'a, HomieID>,
+    },
     Timer(Cow<'a, TimerEvent>),
     Cron(Cow<'a, CronEvent>),
-    Mqtt(Cow<'a, MqttPublishEvent>),
+    Mqtt {
+        event: Cow<'a, MqttPublishEvent>,
+        /// the value extracted from the JSON payload with the `json_path` of the trigger
+        extracted: Option<Cow<'a, serde_json::Value>>,
+    },
     OnSet {
         prop: Cow<'a, PropertyRef>,
         value: Cow<'a, String>,
     },
     Solar(Cow<'a, SolarEvent>),
-    // SystemEvent,
+    System(Cow<'a, SystemEvent>),
+    ValueStore(Cow<'a, ValueStoreEvent>),
+    Webhook(Cow<'a, WebhookRequest>),
+    Broadcast {
+        subtopic: Cow<'a, String>,
+        data: Cow<'a, String>,
+    },
+    /// an event of an inner trigger which matched `count` times within the `window` of a count trigger
+    Counted {
+        event: Box<RuleTriggerEvent<'a>>,
+        count: usize,
+        window: Duration,
+    },
+    /// the events which matched the inner triggers of an all_of trigger, `event` is the one which completed it
+    AllOf {
+        event: Box<RuleTriggerEvent<'a>>,
+        events: Vec<RuleTriggerEvent<'a>>,
+    },
 }
 
-impl RuleTriggerEvent<'_> {
+impl<'a> RuleTriggerEvent<'a> {
+    /// Returns the event which originally caused the trigger, unwrapping events of composite triggers
+    pub fn source_event(&self) -> &RuleTriggerEvent<'a> {
+        match self {
+            Self::Counted { event, .. } | Self::AllOf { event, .. } => event.source_event(),
+            _ => self,
+        }
+    }
+
     pub fn property_ref(&self) -> Option<&PropertyRef> {
-        if let Self::PropertyChanged { prop, .. } | Self::PropertyTriggered { prop, .. } | Self::OnSet { prop, .. } =
-            self
+        if let Self::PropertyChanged { prop, .. }
+        | Self::PropertyTriggered { prop, .. }
+        | Self::PropertyStale { prop, .. }
+        | Self::OnSet { prop, .. } = self.source_event()
         {
             Some(prop)
         } else {
@@ -117,8 +514,47 @@ impl RuleTriggerEvent<'_> {
         }
     }
 
+    pub fn device_ref(&self) -> Option<&DeviceRef> {
+        if let Self::DeviceStateChanged { device, .. }
+        | Self::DeviceAdded { device, .. }
+        | Self::DeviceRemoved { device, .. }
+        | Self::DeviceAlertRaised { device, .. }
+        | Self::DeviceAlertCleared { device, .. } = self.source_event()
+        {
+            Some(device)
+        } else {
+            self.property_ref().map(|prop| prop.device_ref())
+        }
+    }
+
+    pub fn device_state(&self) -> Option<(HomieDeviceStatus, HomieDeviceStatus)> {
+        if let Self::DeviceStateChanged { from, to, .. } = self.source_event() {
+            Some((*from, *to))
+        } else {
+            None
+        }
+    }
+
+    pub fn device_description(&self) -> Option<&HomieDeviceDescription> {
+        if let Self::DeviceAdded { description, .. } | Self::DeviceRemoved { description, .. } = self.source_event() {
+            description.as_ref().as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn alert_id(&self) -> Option<&HomieID> {
+        if let Self::DeviceAlertRaised { alert_id, .. } | Self::DeviceAlertCleared { alert_id, .. } =
+            self.source_event()
+        {
+            Some(alert_id)
+        } else {
+            None
+        }
+    }
+
     pub fn value(&self) -> Option<&HomieValue> {
-        if let Self::PropertyChanged { to, .. } | Self::PropertyTriggered { value: to, .. } = self {
+        if let Self::PropertyChanged { to, .. } | Self::PropertyTriggered { value: to, .. } = self.source_event() {
             Some(to)
         } else {
             None
@@ -126,7 +562,7 @@ impl RuleTriggerEvent<'_> {
     }
 
     pub fn on_set_value(&self) -> Option<&str> {
-        if let Self::OnSet { value, .. } = self {
+        if let Self::OnSet { value, .. } = self.source_event() {
             Some(value)
         } else {
             None
@@ -134,15 +570,23 @@ impl RuleTriggerEvent<'_> {
     }
 
     pub fn from(&self) -> Option<&HomieValue> {
-        if let Self::PropertyChanged { from, .. } = self {
+        if let Self::PropertyChanged { from, .. } = self.source_event() {
             from.as_ref().as_ref()
         } else {
             None
         }
     }
 
+    pub fn last_received(&self) -> Option<&DateTime<Utc>> {
+        if let Self::PropertyStale { last_received, .. } = self.source_event() {
+            last_received.as_ref()
+        } else {
+            None
+        }
+    }
+
     pub fn timer_id(&self) -> Option<&str> {
-        if let RuleTriggerEvent::Timer(event) = self {
+        if let RuleTriggerEvent::Timer(event) = self.source_event() {
             Some(event.id.as_str())
         } else {
             None
@@ -153,24 +597,109 @@ impl RuleTriggerEvent<'_> {
         match self {
             RuleTriggerEvent::PropertyChanged { .. } => "changed",
             RuleTriggerEvent::PropertyTriggered { .. } => "trigered",
+            RuleTriggerEvent::PropertyStale { .. } => "stale",
+            RuleTriggerEvent::DeviceStateChanged { .. } => "device_state",
+            RuleTriggerEvent::DeviceAdded { .. } => "device_added",
+            RuleTriggerEvent::DeviceRemoved { .. } => "device_removed",
+            RuleTriggerEvent::DeviceAlertRaised { .. } => "alert_raised",
+            RuleTriggerEvent::DeviceAlertCleared { .. } => "alert_cleared",
             RuleTriggerEvent::Timer(_) => "timer",
             RuleTriggerEvent::Cron(_) => "cron",
             RuleTriggerEvent::Solar(_) => "solar",
             RuleTriggerEvent::OnSet { .. } => "onset",
-            RuleTriggerEvent::Mqtt(_) => "mqtt",
+            RuleTriggerEvent::Mqtt { .. } => "mqtt",
+            RuleTriggerEvent::System(_) => "system",
+            RuleTriggerEvent::ValueStore(_) => "value_store",
+            RuleTriggerEvent::Webhook(_) => "webhook",
+            RuleTriggerEvent::Broadcast { .. } => "broadcast",
+            RuleTriggerEvent::Counted { .. } => "count",
+            RuleTriggerEvent::AllOf { .. } => "all_of",
+        }
+    }
+
+    pub fn count(&self) -> Option<usize> {
+        if let RuleTriggerEvent::Counted { count, .. } = self {
+            Some(*count)
+        } else {
+            None
+        }
+    }
+
+    pub fn window(&self) -> Option<Duration> {
+        if let RuleTriggerEvent::Counted { window, .. } = self {
+            Some(*window)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the events which matched the inner triggers of an all_of trigger
+    pub fn all_of_events(&self) -> Option<&[RuleTriggerEvent<'a>]> {
+        if let RuleTriggerEvent::AllOf { events, .. } = self {
+            Some(events)
+        } else {
+            None
+        }
+    }
+
+    pub fn system_event(&self) -> Option<&SystemEvent> {
+        if let RuleTriggerEvent::System(event) = self.source_event() {
+            Some(event)
+        } else {
+            None
+        }
+    }
+
+    pub fn value_store_event(&self) -> Option<&ValueStoreEvent> {
+        if let RuleTriggerEvent::ValueStore(event) = self.source_event() {
+            Some(event)
+        } else {
+            None
+        }
+    }
+
+    pub fn webhook_request(&self) -> Option<&WebhookRequest> {
+        if let RuleTriggerEvent::Webhook(request) = self.source_event() {
+            Some(request)
+        } else {
+            None
+        }
+    }
+
+    pub fn broadcast_subtopic(&self) -> Option<&str> {
+        if let RuleTriggerEvent::Broadcast { subtopic, .. } = self.source_event() {
+            Some(subtopic)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the random offset a cron or solar trigger with a jitter fired with
+    pub fn jitter_offset(&self) -> Option<TimeDelta> {
+        match self.source_event() {
+            RuleTriggerEvent::Cron(event) => event.jitter_offset,
+            RuleTriggerEvent::Solar(event) => event.jitter_offset,
+            _ => None,
         }
     }
 
     pub fn mqtt_topic(&self) -> Option<&str> {
-        if let RuleTriggerEvent::Mqtt(me) = self {
-            Some(&me.topic)
+        if let RuleTriggerEvent::Mqtt { event, .. } = self.source_event() {
+            Some(&event.topic)
         } else {
             None
         }
     }
     pub fn mqtt_retain(&self) -> Option<bool> {
-        if let RuleTriggerEvent::Mqtt(me) = self {
-            Some(me.retain)
+        if let RuleTriggerEvent::Mqtt { event, .. } = self.source_event() {
+            Some(event.retain)
+        } else {
+            None
+        }
+    }
+    pub fn mqtt_payload(&self) -> Option<&[u8]> {
+        if let RuleTriggerEvent::Mqtt { event, .. } = self.source_event() {
+            Some(&event.payload)
         } else {
             None
         }
@@ -187,14 +716,68 @@ impl RuleTriggerEvent<'_> {
                 prop: Cow::Owned(prop.clone().into_owned()),
                 value: Cow::Owned(value.clone().into_owned()),
             },
+            RuleTriggerEvent::PropertyStale {
+                prop,
+                last_value,
+                last_received,
+            } => RuleTriggerEvent::PropertyStale {
+                prop: Cow::Owned(prop.clone().into_owned()),
+                last_value: Cow::Owned(last_value.clone().into_owned()),
+                last_received: *last_received,
+            },
+            RuleTriggerEvent::DeviceStateChanged { device, from, to } => RuleTriggerEvent::DeviceStateChanged {
+                device: Cow::Owned(device.clone().into_owned()),
+                from: *from,
+                to: *to,
+            },
+            RuleTriggerEvent::DeviceAdded { device, description } => RuleTriggerEvent::DeviceAdded {
+                device: Cow::Owned(device.clone().into_owned()),
+                description: Cow::Owned(description.clone().into_owned()),
+            },
+            RuleTriggerEvent::DeviceRemoved { device, description } => RuleTriggerEvent::DeviceRemoved {
+                device: Cow::Owned(device.clone().into_owned()),
+                description: Cow::Owned(description.clone().into_owned()),
+            },
+            RuleTriggerEvent::DeviceAlertRaised {
+                device,
+                alert_id,
+                message,
+            } => RuleTriggerEvent::DeviceAlertRaised {
+                device: Cow::Owned(device.clone().into_owned()),
+                alert_id: Cow::Owned(alert_id.clone().into_owned()),
+                message: Cow::Owned(message.clone().into_owned()),
+            },
+            RuleTriggerEvent::DeviceAlertCleared { device, alert_id } => RuleTriggerEvent::DeviceAlertCleared {
+                device: Cow::Owned(device.clone().into_owned()),
+                alert_id: Cow::Owned(alert_id.clone().into_owned()),
+            },
             RuleTriggerEvent::Timer(data) => RuleTriggerEvent::Timer(Cow::Owned(data.clone().into_owned())),
             RuleTriggerEvent::Cron(data) => RuleTriggerEvent::Cron(Cow::Owned(data.clone().into_owned())),
-            RuleTriggerEvent::Mqtt(data) => RuleTriggerEvent::Mqtt(Cow::Owned(data.clone().into_owned())),
+            RuleTriggerEvent::Mqtt { event, extracted } => RuleTriggerEvent::Mqtt {
+                event: Cow::Owned(event.clone().into_owned()),
+                extracted: extracted.clone().map(|extracted| Cow::Owned(extracted.into_owned())),
+            },
             RuleTriggerEvent::Solar(data) => RuleTriggerEvent::Solar(Cow::Owned(data.clone().into_owned())),
+            RuleTriggerEvent::System(data) => RuleTriggerEvent::System(Cow::Owned(data.clone().into_owned())),
+            RuleTriggerEvent::ValueStore(data) => RuleTriggerEvent::ValueStore(Cow::Owned(data.clone().into_owned())),
+            RuleTriggerEvent::Webhook(data) => RuleTriggerEvent::Webhook(Cow::Owned(data.clone().into_owned())),
+            RuleTriggerEvent::Broadcast { subtopic, data } => RuleTriggerEvent::Broadcast {
+                subtopic: Cow::Owned(subtopic.clone().into_owned()),
+                data: Cow::Owned(data.clone().into_owned()),
+            },
             RuleTriggerEvent::OnSet { prop, value } => RuleTriggerEvent::OnSet {
                 prop: Cow::Owned(prop.clone().into_owned()),
                 value: Cow::Owned(value.clone().into_owned()),
             },
+            RuleTriggerEvent::Counted { event, count, window } => RuleTriggerEvent::Counted {
+                event: Box::new(RuleTriggerEvent::to_owned(event)),
+                count: *count,
+                window: *window,
+            },
+            RuleTriggerEvent::AllOf { event, events } => RuleTriggerEvent::AllOf {
+                event: Box::new(RuleTriggerEvent::to_owned(event)),
+                events: events.iter().map(RuleTriggerEvent::to_owned).collect(),
+            },
         }
     }
 }
@@ -214,6 +797,34 @@ impl TryFrom<DiscoveryAction> for RuleTriggerEvent<'_> {
                 prop: Cow::Owned(prop),
                 value: Cow::Owned(value),
             }),
+            DiscoveryAction::StateChanged { device, from, to } => Ok(RuleTriggerEvent::DeviceStateChanged {
+                device: Cow::Owned(device),
+                from,
+                to,
+            }),
+            DiscoveryAction::DeviceRemoved(device) => Ok(RuleTriggerEvent::DeviceRemoved {
+                device: Cow::Owned(device.ident),
+                description: Cow::Owned(device.description),
+            }),
+            DiscoveryAction::DeviceAlert {
+                device,
+                alert_id,
+                alert,
+            }
+            | DiscoveryAction::DeviceAlertChanged {
+                device,
+                alert_id,
+                to_alert: alert,
+                ..
+            } => Ok(RuleTriggerEvent::DeviceAlertRaised {
+                device: Cow::Owned(device),
+                alert_id: Cow::Owned(alert_id),
+                message: Cow::Owned(alert),
+            }),
+            DiscoveryAction::DeviceAlertCleared { device, alert_id } => Ok(RuleTriggerEvent::DeviceAlertCleared {
+                device: Cow::Owned(device),
+                alert_id: Cow::Owned(alert_id),
+            }),
             _ => Err(eyre!("Cannot convert this variant of DiscoverAction to RuleTriggerEvent")),
         }
     }
@@ -234,6 +845,34 @@ impl<'a> TryFrom<&'a DiscoveryAction> for RuleTriggerEvent<'a> {
                 prop: Cow::Borrowed(prop),
                 value: Cow::Borrowed(value),
             }),
+            DiscoveryAction::StateChanged { device, from, to } => Ok(RuleTriggerEvent::DeviceStateChanged {
+                device: Cow::Borrowed(device),
+                from: *from,
+                to: *to,
+            }),
+            DiscoveryAction::DeviceRemoved(device) => Ok(RuleTriggerEvent::DeviceRemoved {
+                device: Cow::Borrowed(&device.ident),
+                description: Cow::Borrowed(&device.description),
+            }),
+            DiscoveryAction::DeviceAlert {
+                device,
+                alert_id,
+                alert,
+            }
+            | DiscoveryAction::DeviceAlertChanged {
+                device,
+                alert_id,
+                to_alert: alert,
+                ..
+            } => Ok(RuleTriggerEvent::DeviceAlertRaised {
+                device: Cow::Borrowed(device),
+                alert_id: Cow::Borrowed(alert_id),
+                message: Cow::Borrowed(alert),
+            }),
+            DiscoveryAction::DeviceAlertCleared { device, alert_id } => Ok(RuleTriggerEvent::DeviceAlertCleared {
+                device: Cow::Borrowed(device),
+                alert_id: Cow::Borrowed(alert_id),
+            }),
             _ => Err(eyre!("Cannot convert this variant of DiscoverAction to RuleTriggerEvent")),
         }
     }
@@ -248,6 +887,10 @@ impl<'a> TryFrom<&'a Homie5Message> for RuleTriggerEvent<'a> {
                 prop: Cow::Borrowed(property),
                 value: Cow::Borrowed(set_value),
             }),
+            Homie5Message::Broadcast { subtopic, data, .. } => Ok(RuleTriggerEvent::Broadcast {
+                subtopic: Cow::Borrowed(subtopic),
+                data: Cow::Borrowed(data),
+            }),
             _ => Err(eyre!("Cannot convert this variant of Homie5Message to RuleTriggerEvent")),
         }
     }
@@ -279,13 +922,19 @@ impl<'a> From<&'a CronEvent> for RuleTriggerEvent<'a> {
 
 impl From<MqttPublishEvent> for RuleTriggerEvent<'_> {
     fn from(event: MqttPublishEvent) -> Self {
-        RuleTriggerEvent::Mqtt(Cow::Owned(event))
+        RuleTriggerEvent::Mqtt {
+            event: Cow::Owned(event),
+            extracted: None,
+        }
     }
 }
 
 impl<'a> From<&'a MqttPublishEvent> for RuleTriggerEvent<'a> {
     fn from(event: &'a MqttPublishEvent) -> Self {
-        RuleTriggerEvent::Mqtt(Cow::Borrowed(event))
+        RuleTriggerEvent::Mqtt {
+            event: Cow::Borrowed(event),
+            extracted: None,
+        }
     }
 }
 
@@ -301,6 +950,172 @@ impl<'a> From<&'a SolarEvent> for RuleTriggerEvent<'a> {
     }
 }
 
+impl From<SystemEvent> for RuleTriggerEvent<'_> {
+    fn from(event: SystemEvent) -> Self {
+        RuleTriggerEvent::System(Cow::Owned(event))
+    }
+}
+
+impl<'a> From<&'a SystemEvent> for RuleTriggerEvent<'a> {
+    fn from(event: &'a SystemEvent) -> Self {
+        RuleTriggerEvent::System(Cow::Borrowed(event))
+    }
+}
+
+impl From<ValueStoreEvent> for RuleTriggerEvent<'_> {
+    fn from(event: ValueStoreEvent) -> Self {
+        RuleTriggerEvent::ValueStore(Cow::Owned(event))
+    }
+}
+
+impl<'a> From<&'a ValueStoreEvent> for RuleTriggerEvent<'a> {
+    fn from(event: &'a ValueStoreEvent) -> Self {
+        RuleTriggerEvent::ValueStore(Cow::Borrowed(event))
+    }
+}
+
+impl<'a> From<&'a WebhookRequest> for RuleTriggerEvent<'a> {
+    fn from(request: &'a WebhookRequest) -> Self {
+        RuleTriggerEvent::Webhook(Cow::Borrowed(request))
+    }
+}
+
+/// Condition on the kind of a system event, either a single kind or a list of kinds of which one needs to match
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SystemEventCondition {
+    Single(SystemEventKind),
+    Multiple(Vec<SystemEventKind>),
+}
+
+impl SystemEventCondition {
+    pub fn evaluate(&self, kind: &SystemEventKind) -> bool {
+        match self {
+            SystemEventCondition::Single(k) => k == kind,
+            SystemEventCondition::Multiple(list) => list.contains(kind),
+        }
+    }
+}
+
+/// Conditions of an alert trigger. Without `event` the trigger fires for raised and cleared alerts.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertTrigger {
+    #[serde(default)]
+    pub id: Option<ValueCondition<String>>,
+    /// condition on the alert message, only evaluated for raised alerts
+    #[serde(default)]
+    pub message: Option<ValueCondition<String>>,
+    #[serde(default)]
+    pub event: Option<DeviceAlertEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceAlertEvent {
+    Raised,
+    Cleared,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceLifecycleEvent {
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceStateTrigger {
+    #[serde(default)]
+    pub from: Option<DeviceStatusCondition>,
+    #[serde(default)]
+    pub to: Option<DeviceStatusCondition>,
+}
+
+/// Thresholds of a crossing trigger. After firing, a threshold is re-armed once the value moved back by
+/// more than the hysteresis (below `above - hysteresis` or above `below + hysteresis`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CrossingTrigger {
+    #[serde(default)]
+    pub above: Option<f64>,
+    #[serde(default)]
+    pub below: Option<f64>,
+    #[serde(default)]
+    pub hysteresis: f64,
+}
+
+/// Bounds of a rate of change trigger. `rise` and `fall` are the change of the value within the `window`.
+/// With `per` set they are a slope instead, e.g. `rise: 2` with `per: 1h` means 2 per hour.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateTrigger {
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub window: Duration,
+    #[serde(default)]
+    pub rise: Option<f64>,
+    #[serde(default)]
+    pub fall: Option<f64>,
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub per: Option<Duration>,
+}
+
+/// Limits a trigger to fire at most once per `interval`. With `leading` the first event of an interval
+/// fires immediately, with `trailing` the last event within the interval fires once the interval ended.
+/// Can be configured as a plain duration which is the same as `{ interval: <duration> }`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "ThrottleConfig")]
+pub struct ThrottleOptions {
+    pub interval: Duration,
+    pub leading: bool,
+    pub trailing: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ThrottleConfig {
+    Interval(#[serde(deserialize_with = "deserialize_duration")] Duration),
+    Options {
+        #[serde(deserialize_with = "deserialize_duration")]
+        interval: Duration,
+        #[serde(default = "default_leading")]
+        leading: bool,
+        #[serde(default)]
+        trailing: bool,
+    },
+}
+
+fn default_leading() -> bool {
+    true
+}
+
+impl TryFrom<ThrottleConfig> for ThrottleOptions {
+    type Error = String;
+
+    fn try_from(value: ThrottleConfig) -> Result<Self, Self::Error> {
+        let options = match value {
+            ThrottleConfig::Interval(interval) => ThrottleOptions {
+                interval,
+                leading: true,
+                trailing: false,
+            },
+            ThrottleConfig::Options {
+                interval,
+                leading,
+                trailing,
+            } => ThrottleOptions {
+                interval,
+                leading,
+                trailing,
+            },
+        };
+        if !options.leading && !options.trailing {
+            return Err("throttle needs at least one of leading or trailing enabled".to_string());
+        }
+        Ok(options)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ChangedTrigger {
     #[serde(default)]
@@ -1,15 +1,26 @@
-use chrono::{Datelike, Local, NaiveTime, Weekday as ChronoWeekday};
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeDelta, Utc, Weekday as ChronoWeekday};
 use hc_homie5::value::ValueCondition;
 use homie5::HomieValue;
 use serde::Deserialize;
+use simple_kv_store::normalize_key;
+
+use homie5::{DeviceRef, PropertyRef};
 
-use homie5::PropertyRef;
+use super::{deserialize_optional_duration, deserialize_optional_offset, DeviceStatusCondition};
+use crate::{
+    calendar::{CalendarManager, DateRange},
+    solar_events::{SolarLocation, SolarPhase},
+};
 
+/// A single condition or a list of conditions which all need to be true
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
 pub enum WhileConditionSet {
-    Single(WhileCondition),
+    // lists are tried first, a single condition struct would also accept a list of its field values
     Multiple(Vec<WhileCondition>),
+    Single(WhileCondition),
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -17,6 +28,139 @@ pub enum WhileConditionSet {
 pub enum WhileCondition {
     PropertyWhileCondition(PropertyWhileCondition),
     TimeWhileCondition(TimeWhileCondition),
+    ValueStoreWhileCondition(ValueStoreWhileCondition),
+    TimerWhileCondition(TimerWhileCondition),
+    DeviceStateWhileCondition(DeviceStateWhileCondition),
+    ValueAgeWhileCondition(ValueAgeWhileCondition),
+    AnyOfWhileCondition(AnyOfWhileCondition),
+    AllOfWhileCondition(AllOfWhileCondition),
+    NotWhileCondition(NotWhileCondition),
+}
+
+/// Condition on the current value of a value store key. Values which are not strings are compared JSON encoded,
+/// a missing key has no value.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValueStoreWhileCondition {
+    pub value_store: String,
+    pub condition: ValueCondition<String>,
+}
+
+impl ValueStoreWhileCondition {
+    /// The key normalized like the keys of the value store
+    pub fn key(&self) -> String {
+        normalize_key(&self.value_store)
+    }
+}
+
+/// True while a timer with an id matching the pattern (`*` and `?` wildcards) is running
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimerWhileCondition {
+    pub timer_active: String,
+}
+
+/// Condition on the state of a device. A device with a root device is only `ready` while its root device is ready,
+/// otherwise it has the state of the root device.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceStateWhileCondition {
+    pub device: DeviceRef,
+    pub state: DeviceStatusCondition,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ValueAgeWhileConditionDef {
+    property: PropertyRef,
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    max_age: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    min_age: Option<Duration>,
+    #[serde(default)]
+    since: ValueAgeSince,
+}
+
+/// Condition on the time since a property last received or changed its value. A property which never received a
+/// value has no age and does not match.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "ValueAgeWhileConditionDef")]
+pub struct ValueAgeWhileCondition {
+    pub property: PropertyRef,
+    /// the value is at most this old
+    pub max_age: Option<Duration>,
+    /// the value is at least this old
+    pub min_age: Option<Duration>,
+    pub since: ValueAgeSince,
+}
+
+impl TryFrom<ValueAgeWhileConditionDef> for ValueAgeWhileCondition {
+    type Error = String;
+
+    fn try_from(value: ValueAgeWhileConditionDef) -> Result<Self, Self::Error> {
+        let ValueAgeWhileConditionDef {
+            property,
+            max_age,
+            min_age,
+            since,
+        } = value;
+        if max_age.is_none() && min_age.is_none() {
+            return Err("value age while condition needs at least one of max_age or min_age".to_string());
+        }
+        Ok(ValueAgeWhileCondition {
+            property,
+            max_age,
+            min_age,
+            since,
+        })
+    }
+}
+
+impl ValueAgeWhileCondition {
+    /// Evaluates the condition for the time the value was last received or changed
+    pub fn evaluate(&self, last_received: Option<DateTime<Utc>>, last_changed: Option<DateTime<Utc>>) -> bool {
+        let timestamp = match self.since {
+            ValueAgeSince::Received => last_received,
+            ValueAgeSince::Changed => last_changed,
+        };
+        let Some(timestamp) = timestamp else {
+            return false;
+        };
+        let age = (Utc::now() - timestamp).to_std().unwrap_or_default();
+        self.max_age.is_none_or(|max_age| age <= max_age) && self.min_age.is_none_or(|min_age| age >= min_age)
+    }
+}
+
+/// The event a value age is measured from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueAgeSince {
+    /// the value was last published, even if it did not change
+    #[default]
+    Received,
+    /// the value last changed
+    Changed,
+}
+
+/// True if at least one of the conditions is true
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnyOfWhileCondition {
+    pub any_of: Vec<WhileCondition>,
+}
+
+/// True if all of the conditions are true
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AllOfWhileCondition {
+    pub all_of: Vec<WhileCondition>,
+}
+
+/// Negates a condition, or a list of conditions which all need to be true
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotWhileCondition {
+    pub not: Box<WhileConditionSet>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -25,67 +169,192 @@ pub struct PropertyWhileCondition {
     pub condition: ValueCondition<HomieValue>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TimeWhileConditionDef {
+    #[serde(default)]
+    after: Option<TimeBound>,
+    #[serde(default)]
+    before: Option<TimeBound>,
+    #[serde(default)]
+    weekdays: Option<Vec<Weekday>>,
+    #[serde(default)]
+    dates: Option<Vec<DateRange>>,
+    #[serde(default)]
+    in_calendar: Option<CalendarSelection>,
+    #[serde(default)]
+    not_in_calendar: Option<CalendarSelection>,
+}
+
+/// Condition on the current local time. All configured parts need to match, at least one is required.
 #[derive(Debug, Clone, Deserialize)]
-#[serde(untagged)] // Deserialize based on field structure, without a "type" tag
-pub enum TimeWhileCondition {
-    // All fields are required
-    Standard {
-        after: NaiveTime,
-        before: NaiveTime,
-        weekdays: Vec<Weekday>,
-    },
-
-    // Only `before` is required; `after` is not allowed
-    Before {
-        before: NaiveTime,
-        weekdays: Option<Vec<Weekday>>, // Optional
-    },
-
-    // Only `after` is required; `before` is not allowed
-    After {
-        after: NaiveTime,
-        weekdays: Option<Vec<Weekday>>, // Optional
-    },
-
-    // Only `weekdays` is required
-    Weekdays {
-        weekdays: Vec<Weekday>,
-    },
+#[serde(try_from = "TimeWhileConditionDef")]
+pub struct TimeWhileCondition {
+    /// start of the time window, the window spans midnight if it starts after it ends (e.g. dusk to dawn)
+    pub after: Option<TimeBound>,
+    /// end of the time window
+    pub before: Option<TimeBound>,
+    pub weekdays: Option<Vec<Weekday>>,
+    /// days (or ranges of days) the condition is true on
+    pub dates: Option<Vec<DateRange>>,
+    /// true while the time is within one of the calendars
+    pub in_calendar: Option<CalendarSelection>,
+    /// true while the time is within none of the calendars
+    pub not_in_calendar: Option<CalendarSelection>,
 }
 
-impl TimeWhileCondition {
-    /// Evaluates the condition based on the current date and time.
-    pub fn evaluate(&self) -> bool {
-        let now = Local::now();
-        let current_time = now.time();
-        let current_weekday = now.weekday();
+impl WhileConditionSet {
+    /// Calls `f` for all conditions of the set including the nested ones
+    pub fn visit<'a>(&'a self, f: &mut impl FnMut(&'a WhileCondition)) {
+        match self {
+            WhileConditionSet::Multiple(conditions) => conditions.iter().for_each(|condition| condition.visit(f)),
+            WhileConditionSet::Single(condition) => condition.visit(f),
+        }
+    }
+}
 
+impl WhileCondition {
+    /// Calls `f` for this condition and all conditions nested inside of it
+    pub fn visit<'a>(&'a self, f: &mut impl FnMut(&'a WhileCondition)) {
+        f(self);
         match self {
-            // Standard: all fields are required
-            TimeWhileCondition::Standard {
-                after,
-                before,
-                weekdays,
-            } => current_time >= *after && current_time <= *before && weekdays.contains(&map_weekday(current_weekday)),
-
-            // Before: only `before` is required
-            TimeWhileCondition::Before { before, weekdays } => {
-                current_time <= *before
-                    && weekdays
-                        .as_ref()
-                        .is_none_or(|days| days.contains(&map_weekday(current_weekday)))
+            WhileCondition::AnyOfWhileCondition(AnyOfWhileCondition { any_of: conditions })
+            | WhileCondition::AllOfWhileCondition(AllOfWhileCondition { all_of: conditions }) => {
+                conditions.iter().for_each(|condition| condition.visit(f))
             }
+            WhileCondition::NotWhileCondition(not) => not.not.visit(f),
+            _ => {}
+        }
+    }
+}
+
+impl TryFrom<TimeWhileConditionDef> for TimeWhileCondition {
+    type Error = String;
+
+    fn try_from(value: TimeWhileConditionDef) -> Result<Self, Self::Error> {
+        let TimeWhileConditionDef {
+            after,
+            before,
+            weekdays,
+            dates,
+            in_calendar,
+            not_in_calendar,
+        } = value;
+        if after.is_none()
+            && before.is_none()
+            && weekdays.is_none()
+            && dates.is_none()
+            && in_calendar.is_none()
+            && not_in_calendar.is_none()
+        {
+            return Err("time while condition needs at least one of after, before, weekdays, dates, in_calendar \
+                        or not_in_calendar"
+                .to_string());
+        }
+        Ok(TimeWhileCondition {
+            after,
+            before,
+            weekdays,
+            dates,
+            in_calendar,
+            not_in_calendar,
+        })
+    }
+}
 
-            // After: only `after` is required
-            TimeWhileCondition::After { after, weekdays } => {
-                current_time >= *after
-                    && weekdays
-                        .as_ref()
-                        .is_none_or(|days| days.contains(&map_weekday(current_weekday)))
+/// A bound of a time window: a fixed local time or the time of a solar phase on the current day, optionally
+/// shifted by an offset (e.g. `-30m` for half an hour before sunset)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum TimeBound {
+    Time(NaiveTime),
+    Solar(SolarTimeBound),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SolarTimeBound {
+    pub sun_phase: SolarPhase,
+    #[serde(default, deserialize_with = "deserialize_optional_offset")]
+    pub offset: Option<TimeDelta>,
+}
+
+impl TimeBound {
+    /// Returns the local time of the bound on the day. Returns `None` if the solar phase cannot be calculated.
+    fn resolve(&self, day: NaiveDate, location: &SolarLocation) -> Option<NaiveTime> {
+        match self {
+            TimeBound::Time(time) => Some(*time),
+            TimeBound::Solar(SolarTimeBound { sun_phase, offset }) => {
+                let phase_time = location.phase_time(day, sun_phase)?;
+                Some((phase_time + offset.unwrap_or_default()).with_timezone(&Local).time())
             }
+        }
+    }
+}
 
-            // Weekdays: only `weekdays` is required
-            TimeWhileCondition::Weekdays { weekdays } => weekdays.contains(&map_weekday(current_weekday)),
+/// A single calendar name or a list of calendar names
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum CalendarSelection {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl CalendarSelection {
+    /// Returns true if any of the calendars contains the time
+    fn any_contains(&self, calendars: &CalendarManager, now: DateTime<Local>) -> bool {
+        match self {
+            CalendarSelection::Single(name) => calendars.contains(name, now),
+            CalendarSelection::Multiple(names) => names.iter().any(|name| calendars.contains(name, now)),
+        }
+    }
+}
+
+impl TimeWhileCondition {
+    /// Evaluates the condition based on the current date and time. Solar phases are calculated for the location.
+    pub fn evaluate(&self, calendars: &CalendarManager, location: &SolarLocation) -> bool {
+        let now = Local::now();
+        let current_weekday = map_weekday(now.weekday());
+
+        self.matches_time_window(now, location)
+            && self
+                .weekdays
+                .as_ref()
+                .is_none_or(|days| days.contains(&current_weekday))
+            && self
+                .dates
+                .as_ref()
+                .is_none_or(|dates| dates.iter().any(|range| range.contains(now.date_naive())))
+            && self
+                .in_calendar
+                .as_ref()
+                .is_none_or(|selection| selection.any_contains(calendars, now))
+            && self
+                .not_in_calendar
+                .as_ref()
+                .is_none_or(|selection| !selection.any_contains(calendars, now))
+    }
+
+    /// Returns true if the time is within the `after` and `before` bounds (both inclusive). A window which starts
+    /// after it ends spans midnight, e.g. from dusk until dawn.
+    fn matches_time_window(&self, now: DateTime<Local>, location: &SolarLocation) -> bool {
+        let resolve = |bound: &TimeBound| {
+            let time = bound.resolve(now.date_naive(), location);
+            if time.is_none() {
+                log::debug!("Cannot calculate the time of while condition bound {:?}", bound);
+            }
+            time
+        };
+        let current_time = now.time();
+        match (self.after.as_ref().map(resolve), self.before.as_ref().map(resolve)) {
+            (Some(None), _) | (_, Some(None)) => false,
+            (Some(Some(after)), Some(Some(before))) if after > before => {
+                current_time >= after || current_time <= before
+            }
+            (after, before) => {
+                after.flatten().is_none_or(|after| current_time >= after)
+                    && before.flatten().is_none_or(|before| current_time <= before)
+            }
         }
     }
 }
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::time::Duration;
 
-use chrono::{TimeZone, Utc};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
 use color_eyre::eyre::Result;
 use config_watcher::ConfigItemHash;
 use hc_homie5::value::ValueMatcher;
@@ -9,6 +9,8 @@ use serde::{Deserialize, Serialize};
 use sun::SunPhase;
 use tokio::sync::{mpsc, watch};
 
+use crate::utils::random_jitter;
+
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum SolarPhase {
@@ -87,17 +89,131 @@ impl ValueMatcher for SolarPhase {
     }
 }
 
+/// Location of the observer for the solar calculations
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SolarLocation {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// height in meters above sea level
+    pub height: f64,
+}
+
+impl SolarLocation {
+    /// Calculates the time of the solar phase on the given (UTC) day
+    pub fn phase_time(&self, day: NaiveDate, phase: &SolarPhase) -> Option<DateTime<Utc>> {
+        let day_start_ms = day.and_time(NaiveTime::MIN).and_utc().timestamp_millis();
+        let phase_time_ms =
+            sun::time_at_phase(day_start_ms, SunPhase::from(phase), self.latitude, self.longitude, self.height);
+        Utc.timestamp_millis_opt(phase_time_ms).single()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SolarEventTrigger {
-    pub rule_hash: ConfigItemHash, // Associated rule identifier
-    pub event: SolarEvent,         // Type of solar event trigger
+    pub rule_hash: ConfigItemHash,     // Associated rule identifier
+    pub trigger_index: usize,          // Index of the trigger within the rule
+    pub event: SolarEventKind,         // Type of solar event trigger
+    jitter_offset: TimeDelta,          // Random offset of the next firing
+    last_fired: Option<DateTime<Utc>>, // Solar event time of the last firing (without jitter)
+}
+
+impl SolarEventTrigger {
+    pub fn new(rule_hash: ConfigItemHash, trigger_index: usize, event: SolarEventKind) -> Self {
+        Self {
+            rule_hash,
+            trigger_index,
+            jitter_offset: event.options().jitter.map(random_jitter).unwrap_or_default(),
+            event,
+            last_fired: None,
+        }
+    }
+
+    /// Creates the event for the firing at `event_time` and chooses the jitter offset for the next firing
+    fn fire(&mut self, event_time: DateTime<Utc>) -> SolarEvent {
+        let jitter_offset = self.event.options().jitter.map(|_| self.jitter_offset);
+        if let Some(jitter_offset) = jitter_offset {
+            log::info!(
+                "Solar event {:?} for rule {} fired with jitter offset {}s",
+                self.event,
+                self.rule_hash,
+                jitter_offset.num_milliseconds() as f64 / 1000.0
+            );
+        }
+        self.last_fired = Some(event_time);
+        self.jitter_offset = self.event.options().jitter.map(random_jitter).unwrap_or_default();
+        SolarEvent {
+            rule_hash: self.rule_hash,
+            trigger_index: self.trigger_index,
+            kind: self.event.clone(),
+            jitter_offset,
+        }
+    }
 }
 
+/// A solar event emitted for a trigger
 #[derive(Debug, Clone)]
-pub enum SolarEvent {
-    At(SolarPhase),               // Trigger at a specific solar event
-    After(SolarPhase, Duration),  // Trigger relative to a solar event
-    Before(SolarPhase, Duration), // Trigger relative to a solar event
+pub struct SolarEvent {
+    pub rule_hash: ConfigItemHash,
+    pub trigger_index: usize,
+    pub kind: SolarEventKind,
+    /// the random offset the event fired with if the trigger has a jitter
+    pub jitter_offset: Option<TimeDelta>,
+}
+
+impl SolarEvent {
+    pub fn phase(&self) -> &SolarPhase {
+        self.kind.phase()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SolarEventKind {
+    At(SolarPhase, SolarEventOptions),               // Trigger at a specific solar event
+    After(SolarPhase, Duration, SolarEventOptions),  // Trigger relative to a solar event
+    Before(SolarPhase, Duration, SolarEventOptions), // Trigger relative to a solar event
+}
+
+impl SolarEventKind {
+    pub fn phase(&self) -> &SolarPhase {
+        match self {
+            SolarEventKind::At(phase, _) | SolarEventKind::After(phase, _, _) | SolarEventKind::Before(phase, _, _) => {
+                phase
+            }
+        }
+    }
+
+    pub fn options(&self) -> &SolarEventOptions {
+        match self {
+            SolarEventKind::At(_, options)
+            | SolarEventKind::After(_, _, options)
+            | SolarEventKind::Before(_, _, options) => options,
+        }
+    }
+}
+
+/// Options for the time a solar event fires at: local times of day the event is moved to when it
+/// happens earlier or later (e.g. sunset, but not after 20:00) and a random offset within ±jitter
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SolarEventOptions {
+    pub not_before: Option<NaiveTime>,
+    pub not_after: Option<NaiveTime>,
+    pub jitter: Option<Duration>,
+}
+
+impl SolarEventOptions {
+    /// Moves the event time into the clamped range on the same local day
+    fn clamp(&self, event_time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local_time = event_time.with_timezone(&Local);
+        let clamped = match (self.not_before, self.not_after) {
+            (Some(not_before), _) if local_time.time() < not_before => not_before,
+            (_, Some(not_after)) if local_time.time() > not_after => not_after,
+            _ => return Some(event_time),
+        };
+        Local
+            .from_local_datetime(&local_time.date_naive().and_time(clamped))
+            .earliest()
+            .map(|time| time.with_timezone(&Utc))
+    }
 }
 
 #[derive(Debug)]
@@ -123,9 +239,15 @@ impl SolarEventHandle {
 #[derive(Debug)]
 pub struct SolarEventManager {
     updates: mpsc::Sender<TriggerUpdate>, // Channel for dynamic updates
+    location: SolarLocation,
 }
 
 impl SolarEventManager {
+    /// The location the solar events are calculated for
+    pub fn location(&self) -> &SolarLocation {
+        &self.location
+    }
+
     pub async fn add_trigger(&self, trigger: SolarEventTrigger) -> Result<()> {
         self.updates.send(TriggerUpdate::Add(trigger)).await?;
         Ok(())
@@ -155,16 +277,21 @@ pub fn run_solar_event_task(
     let (event_sender, event_receiver) = mpsc::channel(channel_size);
     let (updates_sender, mut updates_receiver) = mpsc::channel(channel_size);
     let (stop_sender, mut stop_receiver) = watch::channel(false);
+    let location = SolarLocation {
+        latitude,
+        longitude,
+        height,
+    };
 
     let handle = tokio::spawn(async move {
         let mut triggers: HashMap<ConfigItemHash, Vec<SolarEventTrigger>> = HashMap::new(); // Rule-hash -> Triggers
 
         loop {
-            let next_event = calculate_next_event(&triggers, latitude, longitude, height);
+            let next_event = calculate_next_event(&triggers, &location);
 
             let delay = next_event
                 .as_ref()
-                .map(|(_, delay)| *delay)
+                .map(|(_, _, _, delay)| *delay)
                 .unwrap_or(Duration::from_secs(60)); // Default sleep if no event
 
             tokio::select! {
@@ -192,10 +319,14 @@ pub fn run_solar_event_task(
 
                 // Wait for the next event or default delay
                 _ = tokio::time::sleep(delay) => {
-                        if let Some((trigger, _)) = next_event {
-                            log::debug!("Triggering event for rule: {}", trigger.rule_hash);
-                            if let Err(err) = event_sender.send(trigger.event.clone()).await {
-                                log::warn!("Error sending solar event trigger {:?}: {:#?}", trigger, err);
+                        if let Some((rule_hash, index, event_time, _)) = next_event {
+                            let Some(trigger) = triggers.get_mut(&rule_hash).and_then(|triggers| triggers.get_mut(index)) else {
+                                continue;
+                            };
+                            log::debug!("Triggering event for rule: {}", rule_hash);
+                            let event = trigger.fire(event_time);
+                            if let Err(err) = event_sender.send(event).await {
+                                log::warn!("Error sending solar event for rule {}: {:#?}", rule_hash, err);
                             }
                         }
                 }
@@ -207,73 +338,73 @@ pub fn run_solar_event_task(
         SolarEventHandle { stop_sender, handle },
         SolarEventManager {
             updates: updates_sender,
+            location,
         },
         event_receiver,
     )
 }
 
+/// Calculates the next firing of all triggers. Returns the rule hash and index of the trigger, the time of the
+/// solar event (including clamps but without jitter) and the delay until the trigger fires.
 fn calculate_next_event(
-    triggers: &'_ HashMap<ConfigItemHash, Vec<SolarEventTrigger>>,
-    latitude: f64,
-    longitude: f64,
-    height: f64,
-) -> Option<(&'_ SolarEventTrigger, Duration)> {
+    triggers: &HashMap<ConfigItemHash, Vec<SolarEventTrigger>>,
+    location: &SolarLocation,
+) -> Option<(ConfigItemHash, usize, DateTime<Utc>, Duration)> {
     let now = Utc::now();
 
     // Iterate through all triggers and calculate the next valid event
     let mut next_events = triggers
-        .values()
-        .flat_map(|rule_triggers| rule_triggers.iter())
-        .filter_map(|trigger| {
+        .iter()
+        .flat_map(|(rule_hash, rule_triggers)| {
+            rule_triggers
+                .iter()
+                .enumerate()
+                .map(move |(index, trigger)| (*rule_hash, index, trigger))
+        })
+        .filter_map(|(rule_hash, index, trigger)| {
             let mut day = now.naive_utc().date();
-            let time = loop {
-                let today_start = day.and_hms_opt(0, 0, 0).unwrap();
-                let today_start_ms = today_start.and_utc().timestamp_millis();
-
+            let (event_time, fire_time) = loop {
                 // Calculate the event time
-                let event_time_ms = match &trigger.event {
-                    SolarEvent::At(phase) => {
-                        sun::time_at_phase(today_start_ms, SunPhase::from(phase), latitude, longitude, height)
-                    }
-                    SolarEvent::After(phase, offset) => {
-                        let base_time =
-                            sun::time_at_phase(today_start_ms, SunPhase::from(phase), latitude, longitude, height);
-                        base_time + offset.as_millis() as i64
-                    }
-                    SolarEvent::Before(phase, offset) => {
-                        let base_time =
-                            sun::time_at_phase(today_start_ms, SunPhase::from(phase), latitude, longitude, height);
-                        base_time - offset.as_millis() as i64
-                    }
+                let event_time = match &trigger.event {
+                    SolarEventKind::At(phase, _) => location.phase_time(day, phase),
+                    SolarEventKind::After(phase, offset, _) => location
+                        .phase_time(day, phase)
+                        .zip(TimeDelta::from_std(*offset).ok())
+                        .map(|(base_time, offset)| base_time + offset),
+                    SolarEventKind::Before(phase, offset, _) => location
+                        .phase_time(day, phase)
+                        .zip(TimeDelta::from_std(*offset).ok())
+                        .map(|(base_time, offset)| base_time - offset),
                 };
 
-                // Convert the calculated time to a DateTime<Utc>
-                if let Some(event_time) = Utc.timestamp_millis_opt(event_time_ms).single() {
-                    // If the event time is in the past, move to the next day
-                    if event_time <= now {
+                // Apply the configured clamps
+                if let Some(event_time) = event_time.and_then(|event_time| trigger.event.options().clamp(event_time)) {
+                    let fire_time = event_time + trigger.jitter_offset;
+                    // If the event time is in the past or already fired (e.g. early due to jitter), move to the next day
+                    if fire_time <= now || trigger.last_fired.is_some_and(|last_fired| event_time <= last_fired) {
                         day = day.succ_opt()?;
                         continue;
                     }
 
-                    break event_time; // Return the valid future event time
+                    break (event_time, fire_time); // Return the valid future event time
                 } else {
                     return None; // Skip invalid times
                 }
             };
 
-            Some((trigger, time))
+            Some((rule_hash, index, event_time, fire_time))
         })
         .collect::<Vec<_>>();
 
     // Find the next event (closest future event)
-    next_events.sort_by_key(|(_, time)| *time);
+    next_events.sort_by_key(|(_, _, _, fire_time)| *fire_time);
 
     // Map to the first event with a valid delay
     next_events
         .into_iter()
-        .filter_map(|(trigger, time)| {
-            let delay = (time - now).to_std().ok()?;
-            Some((trigger, delay))
+        .filter_map(|(rule_hash, index, event_time, fire_time)| {
+            let delay = (fire_time - now).to_std().ok()?;
+            Some((rule_hash, index, event_time, delay))
         })
         .next()
 }
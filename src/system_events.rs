@@ -0,0 +1,54 @@
+use std::fmt::Display;
+
+use serde::Deserialize;
+
+/// Lifecycle events of the automation controller itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SystemEventKind {
+    /// all connections are established, the watchers are started and discovery settled
+    Startup,
+    /// the connection to the mqtt broker was re-established and devices were rediscovered
+    Reconnect,
+    /// rules or lua files were changed after startup
+    Reload,
+    /// the application is shutting down
+    Shutdown,
+}
+
+impl SystemEventKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SystemEventKind::Startup => "startup",
+            SystemEventKind::Reconnect => "reconnect",
+            SystemEventKind::Reload => "reload",
+            SystemEventKind::Shutdown => "shutdown",
+        }
+    }
+}
+
+impl Display for SystemEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SystemEvent {
+    pub kind: SystemEventKind,
+    /// additional information about the event (e.g. which configuration was reloaded)
+    pub detail: Option<String>,
+}
+
+impl SystemEvent {
+    pub fn new(kind: SystemEventKind) -> Self {
+        Self { kind, detail: None }
+    }
+
+    pub fn with_detail(kind: SystemEventKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            detail: Some(detail.into()),
+        }
+    }
+}
@@ -9,7 +9,10 @@ use tokio::{
     task::JoinHandle,
 };
 
-use crate::rules::{RuleAction, RuleTriggerEvent};
+use crate::{
+    rules::{RuleAction, RuleTriggerEvent},
+    utils::match_wildcard,
+};
 
 #[derive(Debug)]
 pub struct Timer {
@@ -129,6 +132,11 @@ impl TimerManager {
         }
     }
 
+    /// Returns true if a timer with an id matching the pattern (`*` and `?` wildcards) is running
+    pub fn is_active(&self, pattern: &str) -> bool {
+        self.timers.lock().unwrap().keys().any(|id| match_wildcard(pattern, id))
+    }
+
     pub fn remove_timers_for_rule(&self, rule_hash: ConfigItemHash) {
         let mut timers = self.timers.lock().unwrap();
         timers.retain(|id, timer| {
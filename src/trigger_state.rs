@@ -0,0 +1,88 @@
+use crate::rules::RuleTriggerEvent;
+use config_watcher::ConfigItemHash;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+/// Identifies the state of a stateful trigger. Triggers which track state per property or topic
+/// use the subject to keep them apart, triggers with a single state use an empty subject.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TriggerStateKey {
+    pub rule_hash: ConfigItemHash,
+    pub trigger_index: usize,
+    pub subject: String,
+}
+
+impl TriggerStateKey {
+    pub fn new(rule_hash: ConfigItemHash, trigger_index: usize, subject: impl Into<String>) -> Self {
+        Self {
+            rule_hash,
+            trigger_index,
+            subject: subject.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TriggerState {
+    /// progress of a sequence trigger: the index of the next expected step and the time the sequence started
+    Sequence { step: usize, started: Instant },
+    /// armed state of the `above` and `below` thresholds of a crossing trigger for a single property
+    Crossing { above_armed: bool, below_armed: bool },
+    /// values of a single property received within the window of a rate trigger
+    Rate { history: VecDeque<(Instant, f64)> },
+    /// matches of a count trigger within the current window and the end of a running cooldown
+    Count {
+        matches: VecDeque<Instant>,
+        cooldown_until: Option<Instant>,
+    },
+    /// events which matched the inner triggers of an all_of trigger within its window, by trigger index
+    AllOf {
+        matches: Vec<Option<(Instant, RuleTriggerEvent<'static>)>>,
+    },
+    /// end of the running interval of a throttled trigger for a single subject
+    Throttle { until: Instant },
+}
+
+/// Stores the runtime state of stateful rule triggers (e.g. sequences, counters, value histories)
+#[derive(Debug, Clone, Default)]
+pub struct TriggerStateStore {
+    states: Arc<Mutex<HashMap<TriggerStateKey, TriggerState>>>,
+}
+
+impl TriggerStateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gives mutable access to the state for the key. Setting the state to `None` removes it.
+    pub fn update<R>(&self, key: TriggerStateKey, f: impl FnOnce(&mut Option<TriggerState>) -> R) -> R {
+        let mut states = self.states.lock().unwrap();
+        let mut state = states.remove(&key);
+        let result = f(&mut state);
+        if let Some(state) = state {
+            states.insert(key, state);
+        }
+        result
+    }
+
+    /// Removes the throttle states whose interval ended. Throttles are tracked per subject (e.g. every topic
+    /// matched by a wildcard), without this the states of subjects which are not seen again would be kept forever.
+    pub fn remove_expired_throttles(&self, now: Instant) {
+        self.states
+            .lock()
+            .unwrap()
+            .retain(|_, state| !matches!(state, TriggerState::Throttle { until } if *until <= now));
+    }
+
+    pub fn remove_for_rule(&self, rule_hash: ConfigItemHash) {
+        self.states.lock().unwrap().retain(|key, _| key.rule_hash != rule_hash);
+    }
+
+    pub fn clear(&self) {
+        log::debug!("Removing all trigger states");
+        self.states.lock().unwrap().clear();
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::TimeDelta;
 use homie5::{Homie5Message, ToTopic};
 use std::time::Instant;
 use tokio::{
@@ -33,6 +34,39 @@ pub fn throttle_channel<T: Send + 'static>(mut input_rx: mpsc::Receiver<T>, dela
     throttled_rx
 }
 
+/// Returns a random offset within ±`jitter`
+pub fn random_jitter(jitter: Duration) -> TimeDelta {
+    let max = jitter.as_millis() as i64;
+    TimeDelta::milliseconds(rand::random_range(-max..=max))
+}
+
+/// Matches a value against a pattern. `*` matches any number of characters, `?` matches a single character.
+pub fn match_wildcard(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+
+    let (mut p, mut v) = (0, 0);
+    // position of the last `*` in the pattern and the value position it currently matches up to
+    let mut backtrack = None;
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if let Some((star_p, star_v)) = backtrack {
+            // let the last `*` match one more character
+            backtrack = Some((star_p, star_v + 1));
+            p = star_p + 1;
+            v = star_v + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 pub fn log_homie_message(msg: &Homie5Message) -> String {
     match msg {
         Homie5Message::DeviceState { device, state } => {
@@ -0,0 +1,98 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use serde_json::Value;
+use simple_kv_store::{normalize_key, KeyValueStore};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+use crate::utils::match_wildcard;
+
+/// Emitted when a key of the value store was written or deleted by a lua script
+#[derive(Debug, Clone)]
+pub struct ValueStoreEvent {
+    pub key: String,
+    /// the new value, `None` if the key was deleted
+    pub value: Option<Value>,
+}
+
+impl ValueStoreEvent {
+    /// Returns the value as string. String values are returned as they are, all other values JSON encoded.
+    pub fn value_string(&self) -> Option<String> {
+        self.value.as_ref().map(value_string)
+    }
+}
+
+/// Returns the value as string. String values are returned as they are, all other values JSON encoded.
+pub fn value_string(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Forwards value store changes made by lua scripts to the application event loop. Also keeps the last known
+/// values of the keys used by while conditions, so that they can be checked without accessing the store.
+#[derive(Debug, Clone)]
+pub struct ValueStoreNotifier {
+    sender: Sender<ValueStoreEvent>,
+    // Maps key -> last known value, `None` if the key is deleted
+    values: Arc<Mutex<HashMap<String, Option<Value>>>>,
+}
+
+impl ValueStoreNotifier {
+    pub fn new() -> (Self, Receiver<ValueStoreEvent>) {
+        let (sender, receiver) = mpsc::channel(1024);
+        (
+            Self {
+                sender,
+                values: Arc::new(Mutex::new(HashMap::new())),
+            },
+            receiver,
+        )
+    }
+
+    pub fn notify(&self, key: impl Into<String>, value: Option<Value>) {
+        let key = key.into();
+        // only the keys loaded for while conditions are kept
+        if let Some(known) = self.values.lock().unwrap().get_mut(&key) {
+            known.clone_from(&value);
+        }
+        // scripts run inside the event loop which consumes these events, waiting for free capacity
+        // in the channel could therefore block forever
+        if let Err(err) = self.sender.try_send(ValueStoreEvent { key, value }) {
+            log::warn!("Error sending value store event: {}", err);
+        }
+    }
+
+    /// Returns the last known value of the (normalized) key. Keys are known once they were loaded.
+    pub fn value(&self, key: &str) -> Option<Value> {
+        self.values.lock().unwrap().get(key).cloned().flatten()
+    }
+
+    /// Loads the values of (normalized) keys which are not known yet from the store
+    pub async fn load_values(&self, store: &KeyValueStore, keys: impl IntoIterator<Item = String>) {
+        for key in keys {
+            if self.values.lock().unwrap().contains_key(&key) {
+                continue;
+            }
+            let value: Option<Value> = store.get(&key).await;
+            // a value written while loading is newer than the loaded one
+            self.values.lock().unwrap().entry(key).or_insert(value);
+        }
+    }
+}
+
+/// Matches a value store key against a pattern. `*` matches any number of characters, `?` matches a
+/// single character. The pattern is normalized the same way as the keys of the value store.
+pub fn match_key_pattern(pattern: &str, key: &str) -> bool {
+    let pattern: String = pattern
+        .chars()
+        .map(|c| match c {
+            '*' | '?' => c.to_string(),
+            _ => normalize_key(c.encode_utf8(&mut [0; 4])),
+        })
+        .collect();
+    match_wildcard(&pattern, key)
+}
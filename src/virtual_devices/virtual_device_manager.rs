@@ -9,7 +9,7 @@ use homie5::{device_description::DeviceDescriptionBuilder, Homie5ControllerProto
 use tokio::sync::{mpsc, RwLock};
 
 use crate::{
-    app_state::AppEvent, cfg_files_tracker::CfgFilesTracker, device_manager::DeviceManager,
+    app_state::AppEvent, cfg_files_tracker::CfgFilesTracker, device_manager::DeviceManager, homie::HomieBroadcaster,
     mqtt_client::ManagedMqttClient,
 };
 
@@ -30,7 +30,6 @@ pub struct VirtualDeviceManagerConfig {
 #[derive(Clone)]
 pub struct VirtualDeviceManager {
     devices: Arc<RwLock<HashMap<DeviceRef, VirtualDevice>>>,
-    #[allow(dead_code)]
     ctrl_proto: Homie5ControllerProtocol,
     dm: DeviceManager,
     mqtt_client: ManagedMqttClient,
@@ -298,6 +297,11 @@ impl VirtualDeviceManager {
         Ok(())
     }
 
+    /// Returns a broadcaster publishing homie broadcasts via the controller's device client
+    pub fn broadcaster(&self) -> HomieBroadcaster {
+        HomieBroadcaster::new(self.ctrl_proto.clone(), self.homie_client.clone(), self.homie_domain().clone())
+    }
+
     pub fn as_proxy(&self) -> VirtualDeviceManagerProxy {
         VirtualDeviceManagerProxy::new(Arc::clone(&self.devices))
     }
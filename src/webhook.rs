@@ -0,0 +1,211 @@
+use std::{collections::BTreeMap, convert::Infallible, net::SocketAddr, time::Duration};
+
+use color_eyre::eyre::Result;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::{
+    body::{Bytes, Incoming},
+    server::conn::http1,
+    service::service_fn,
+    Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc, oneshot, watch},
+};
+
+/// maximum accepted size of a webhook request body
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+/// header carrying the shared secret, it is removed from the request passed to the rules
+const SECRET_HEADER: &str = "x-webhook-secret";
+/// query parameter which carried the shared secret in earlier versions, it is removed from the request as well
+const SECRET_QUERY_PARAM: &str = "secret";
+/// time to wait for the event loop to evaluate the request before answering with 503
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An http request received by the webhook listener. The shared secret is not part of the request, so it
+/// does not end up in the events passed to the actions and lua scripts.
+#[derive(Debug, Clone)]
+pub struct WebhookRequest {
+    pub method: String,
+    pub path: String,
+    /// request headers with lowercase names, multiple values of the same header are joined by `, `
+    pub headers: BTreeMap<String, String>,
+    pub query: BTreeMap<String, String>,
+    pub body: String,
+}
+
+/// Result of the rule evaluation for a webhook request which is answered to the http client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookResponse {
+    /// at least one webhook trigger matched the request
+    Accepted,
+    /// a webhook trigger matched path and method, but the secret was missing or wrong
+    Unauthorized,
+    /// no webhook trigger is configured for path and method
+    NotFound,
+}
+
+impl WebhookResponse {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            WebhookResponse::Accepted => StatusCode::ACCEPTED,
+            WebhookResponse::Unauthorized => StatusCode::UNAUTHORIZED,
+            WebhookResponse::NotFound => StatusCode::NOT_FOUND,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct WebhookEvent {
+    pub request: WebhookRequest,
+    /// the shared secret provided via the `x-webhook-secret` header
+    pub secret: Option<String>,
+    /// receives the result of the rule evaluation, the request is answered once this is sent
+    pub response: oneshot::Sender<WebhookResponse>,
+}
+
+pub struct WebhookListenerHandle {
+    stop_sender: watch::Sender<bool>,
+    handle: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl WebhookListenerHandle {
+    /// Stops the webhook listener task.
+    pub async fn stop(self) {
+        let _ = self.stop_sender.send(true);
+        if let Some(handle) = self.handle {
+            let _ = handle.await;
+        }
+    }
+}
+
+/// Starts the embedded http listener for webhook triggers.
+///
+/// # Arguments
+/// - `addr`: Address to listen on, if `None` the listener is disabled and no events are emitted.
+/// - `channel_size`: Size of the channel buffer for events.
+///
+/// # Returns
+/// A tuple containing the `WebhookListenerHandle` to stop the task and a receiver for webhook events.
+pub async fn run_webhook_listener(
+    addr: Option<SocketAddr>,
+    channel_size: usize,
+) -> Result<(WebhookListenerHandle, mpsc::Receiver<WebhookEvent>)> {
+    let (event_sender, event_receiver) = mpsc::channel(channel_size);
+    let (stop_sender, mut stop_receiver) = watch::channel(false);
+
+    let Some(addr) = addr else {
+        return Ok((
+            WebhookListenerHandle {
+                stop_sender,
+                handle: None,
+            },
+            event_receiver,
+        ));
+    };
+
+    let listener = TcpListener::bind(addr).await?;
+    log::info!("Webhook listener started on {}", addr);
+
+    let handle = tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, remote) = match accepted {
+                        Ok(accepted) => accepted,
+                        Err(err) => {
+                            log::warn!("Error accepting webhook connection: {}", err);
+                            continue;
+                        }
+                    };
+                    let event_sender = event_sender.clone();
+                    tokio::spawn(async move {
+                        let service = service_fn(move |req| handle_request(req, event_sender.clone()));
+                        if let Err(err) = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await {
+                            log::debug!("Error serving webhook connection from {}: {}", remote, err);
+                        }
+                    });
+                }
+
+                // Stop signal received
+                _ = stop_receiver.changed() => {
+                    if *stop_receiver.borrow() {
+                        log::debug!("Stopping webhook listener...");
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok((
+        WebhookListenerHandle {
+            stop_sender,
+            handle: Some(handle),
+        },
+        event_receiver,
+    ))
+}
+
+async fn handle_request(
+    req: Request<Incoming>,
+    event_sender: mpsc::Sender<WebhookEvent>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let mut query: BTreeMap<String, String> = req
+        .uri()
+        .query()
+        .map(|query| form_urlencoded::parse(query.as_bytes()).into_owned().collect())
+        .unwrap_or_default();
+    query.remove(SECRET_QUERY_PARAM);
+    let mut headers: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in req.headers() {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        headers
+            .entry(name.to_string())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+    let secret = headers.remove(SECRET_HEADER);
+
+    let body = match Limited::new(req.into_body(), MAX_BODY_SIZE).collect().await {
+        Ok(body) => String::from_utf8_lossy(&body.to_bytes()).to_string(),
+        Err(err) => {
+            log::debug!("Error reading webhook request body for {}: {}", path, err);
+            return Ok(status_response(StatusCode::PAYLOAD_TOO_LARGE));
+        }
+    };
+
+    let (response_sender, response_receiver) = oneshot::channel();
+    let event = WebhookEvent {
+        request: WebhookRequest {
+            method,
+            path,
+            headers,
+            query,
+            body,
+        },
+        secret,
+        response: response_sender,
+    };
+    if let Err(err) = event_sender.send(event).await {
+        log::warn!("Error sending webhook event: {}", err);
+        return Ok(status_response(StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    match tokio::time::timeout(RESPONSE_TIMEOUT, response_receiver).await {
+        Ok(Ok(response)) => Ok(status_response(response.status_code())),
+        _ => Ok(status_response(StatusCode::SERVICE_UNAVAILABLE)),
+    }
+}
+
+fn status_response(status: StatusCode) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(status.canonical_reason().unwrap_or_default().to_string())));
+    *response.status_mut() = status;
+    response
+}